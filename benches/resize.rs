@@ -0,0 +1,75 @@
+//! Compares the `image` and the `fast` resize backends on representative
+//! screenshot and photo sizes.
+//! Run with `cargo bench --features fast-resize`.
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use image::{Rgba, RgbaImage};
+use picst::{
+    planner::ResizeTarget,
+    resize::{resize_image, ResizeBackend, ResizeOptions},
+};
+
+/// Simple type alias as (width, height).
+type Dimensions = (u32, u32);
+
+/// Cases as tuples of (name, source dimensions, target dimensions).
+static CASES: &[(&str, Dimensions, Dimensions)] = &[
+    ("screenshot-1080p", (1920, 1080), (960, 540)),
+    ("screenshot-6k", (6016, 3384), (3008, 1692)),
+    ("photo-12mp", (4000, 3000), (1600, 1200)),
+];
+
+/// Generates an image with gradients and sharp edges, close enough to real
+/// content for the filters to do actual work.
+fn generate_image(width: u32, height: u32) -> RgbaImage {
+    RgbaImage::from_fn(width, height, |x, y| {
+        let edge = if (x / 7 + y / 5) % 2 == 0 { 40 } else { 0 };
+
+        Rgba([
+            ((x * 255 / width) as u8).saturating_add(edge),
+            ((y * 255 / height) as u8).saturating_add(edge),
+            ((x ^ y) & 0xff) as u8,
+            255,
+        ])
+    })
+}
+
+fn bench_backends(c: &mut Criterion) {
+    let mut group = c.benchmark_group("resize");
+
+    // Large images make each iteration slow, keep the run reasonable.
+    group.sample_size(10);
+
+    for (name, (width, height), (target_width, target_height)) in CASES {
+        let image_buffer = generate_image(*width, *height);
+        let target = ResizeTarget::Dimensions((*target_height, *target_width));
+
+        for (backend_name, backend) in [
+            ("image", ResizeBackend::Image),
+            ("fast", ResizeBackend::Fast),
+        ] {
+            let options = ResizeOptions {
+                backend,
+                ..ResizeOptions::default()
+            };
+
+            // The copy of the source image is left out of the measurement.
+            group.bench_with_input(
+                BenchmarkId::new(backend_name, name),
+                &image_buffer,
+                |b, image| {
+                    b.iter_batched(
+                        || image.clone(),
+                        |image| resize_image(image, &target, &options),
+                        BatchSize::LargeInput,
+                    );
+                },
+            );
+        }
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_backends);
+criterion_main!(benches);
@@ -0,0 +1,52 @@
+use std::convert::TryFrom;
+
+use anyhow::Result;
+
+/// Adjustments used for the select prompt.
+static ADJUSTMENTS: &[&str; 3] = &["Accept", "Adjust the values", "Pick another unit"];
+
+/// Enumeration for the adjustment of the prompted size.
+#[derive(Debug, PartialEq)]
+pub(crate) enum Adjustment {
+    Accept = 0,
+    Values = 1,
+    Unit = 2,
+}
+
+impl Adjustment {
+    /// Returns the static adjustments.
+    pub(crate) fn get_items() -> &'static [&'static str; 3] {
+        ADJUSTMENTS
+    }
+}
+
+impl TryFrom<usize> for Adjustment {
+    type Error = &'static str;
+
+    fn try_from(value: usize) -> Result<Self, Self::Error> {
+        match value {
+            u if u == Adjustment::Accept as usize => Ok(Adjustment::Accept),
+            u if u == Adjustment::Values as usize => Ok(Adjustment::Values),
+            u if u == Adjustment::Unit as usize => Ok(Adjustment::Unit),
+            // Unreachable.
+            _ => Err("Index cannot be converted to adjustment."),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{Adjustment, ADJUSTMENTS};
+
+    #[test]
+    fn check_adjustment() {
+        assert_eq!(Adjustment::get_items(), ADJUSTMENTS);
+
+        let zero_to_adjustment: Adjustment = 0usize.try_into().unwrap();
+        assert_eq!(zero_to_adjustment, Adjustment::Accept);
+
+        let two_to_adjustment: Adjustment = 2usize.try_into().unwrap();
+        assert_eq!(two_to_adjustment, Adjustment::Unit);
+    }
+}
@@ -0,0 +1,163 @@
+use image::{Rgb, Rgba, Rgba32FImage, RgbaImage};
+
+/// Checks if at least one pixel of the image buffer is not fully opaque.
+pub(crate) fn has_transparency(image_buffer: &RgbaImage) -> bool {
+    image_buffer.pixels().any(|pixel| pixel[3] != u8::MAX)
+}
+
+/// Composites the image buffer onto an opaque background color.
+pub(crate) fn flatten(image_buffer: &mut RgbaImage, background: Rgb<u8>) {
+    for pixel in image_buffer.pixels_mut() {
+        let alpha = u32::from(pixel[3]);
+
+        for (channel, background_channel) in pixel.0.iter_mut().zip(background.0) {
+            *channel = ((u32::from(*channel) * alpha
+                + u32::from(background_channel) * (255 - alpha)
+                + 127)
+                / 255) as u8;
+        }
+
+        pixel[3] = u8::MAX;
+    }
+}
+
+/// Makes the image buffer fully opaque, keeping the color channels as is.
+pub(crate) fn strip_alpha(image_buffer: &mut RgbaImage) {
+    for pixel in image_buffer.pixels_mut() {
+        pixel[3] = u8::MAX;
+    }
+}
+
+/// Decodes a channel to the unit interval.
+pub(crate) fn decode_channel(value: u8) -> f32 {
+    f32::from(value) / 255.
+}
+
+/// Encodes a channel from the unit interval, clamping the values overshot by
+/// the resampling.
+pub(crate) fn encode_channel(value: f32) -> u8 {
+    (value.clamp(0., 1.) * 255.).round() as u8
+}
+
+/// Converts the image buffer to float precision, the color channels being
+/// decoded with the function and multiplied by the alpha channel, so that
+/// transparent pixels don't bleed their color when resampled.
+/// Note: the alpha channel is kept as is.
+pub(crate) fn premultiply(image_buffer: &RgbaImage, decode: impl Fn(u8) -> f32) -> Rgba32FImage {
+    Rgba32FImage::from_fn(image_buffer.width(), image_buffer.height(), |x, y| {
+        let Rgba([red, green, blue, alpha]) = *image_buffer.get_pixel(x, y);
+        let alpha = decode_channel(alpha);
+
+        Rgba([
+            decode(red) * alpha,
+            decode(green) * alpha,
+            decode(blue) * alpha,
+            alpha,
+        ])
+    })
+}
+
+/// Reverts `premultiply` by dividing the color channels by the alpha
+/// channel, the color channels being encoded back with the function.
+pub(crate) fn unpremultiply(image_buffer: &Rgba32FImage, encode: impl Fn(f32) -> u8) -> RgbaImage {
+    RgbaImage::from_fn(image_buffer.width(), image_buffer.height(), |x, y| {
+        let Rgba([red, green, blue, alpha]) = *image_buffer.get_pixel(x, y);
+        let alpha = alpha.clamp(0., 1.);
+
+        if alpha == 0. {
+            return Rgba([0, 0, 0, 0]);
+        }
+
+        Rgba([
+            encode(red / alpha),
+            encode(green / alpha),
+            encode(blue / alpha),
+            encode_channel(alpha),
+        ])
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{imageops, Rgb, Rgba, RgbaImage};
+
+    use super::{
+        decode_channel, encode_channel, flatten, has_transparency, premultiply, strip_alpha,
+        unpremultiply,
+    };
+
+    #[test]
+    fn check_has_transparency() {
+        let mut image_buffer = RgbaImage::from_pixel(2, 2, Rgba([0, 0, 0, 255]));
+        assert!(!has_transparency(&image_buffer));
+
+        image_buffer.put_pixel(1, 1, Rgba([0, 0, 0, 254]));
+        assert!(has_transparency(&image_buffer));
+    }
+
+    #[test]
+    fn check_flatten() {
+        let mut image_buffer = RgbaImage::from_pixel(1, 2, Rgba([0, 0, 0, 0]));
+        image_buffer.put_pixel(0, 1, Rgba([255, 0, 0, 128]));
+
+        flatten(&mut image_buffer, Rgb([255, 255, 255]));
+
+        assert_eq!(*image_buffer.get_pixel(0, 0), Rgba([255, 255, 255, 255]));
+        assert_eq!(*image_buffer.get_pixel(0, 1), Rgba([255, 127, 127, 255]));
+    }
+
+    #[test]
+    fn check_strip_alpha() {
+        let mut image_buffer = RgbaImage::from_pixel(1, 1, Rgba([10, 20, 30, 0]));
+
+        strip_alpha(&mut image_buffer);
+
+        assert_eq!(*image_buffer.get_pixel(0, 0), Rgba([10, 20, 30, 255]));
+    }
+
+    #[test]
+    fn check_premultiply_round_trip() {
+        let image_buffer = RgbaImage::from_fn(4, 1, |x, _| {
+            Rgba([200, 100, 50, [255, 128, 1, 0][x as usize]])
+        });
+
+        let premultiplied = premultiply(&image_buffer, decode_channel);
+        assert_eq!(premultiplied.get_pixel(3, 0).0, [0., 0., 0., 0.]);
+
+        // The colors are kept at float precision, even when almost
+        // transparent.
+        let unpremultiplied = unpremultiply(&premultiplied, encode_channel);
+        assert_eq!(*unpremultiplied.get_pixel(0, 0), Rgba([200, 100, 50, 255]));
+        assert_eq!(*unpremultiplied.get_pixel(1, 0), Rgba([200, 100, 50, 128]));
+        assert_eq!(*unpremultiplied.get_pixel(2, 0), Rgba([200, 100, 50, 1]));
+        assert_eq!(*unpremultiplied.get_pixel(3, 0), Rgba([0, 0, 0, 0]));
+    }
+
+    #[test]
+    fn check_premultiplied_resize_has_no_dark_fringe() {
+        // Opaque white next to fully transparent black.
+        let image_buffer = RgbaImage::from_fn(8, 1, |x, _| {
+            if x < 4 {
+                Rgba([255, 255, 255, 255])
+            } else {
+                Rgba([0, 0, 0, 0])
+            }
+        });
+
+        // Without premultiplication, the black of the transparent pixels
+        // bleeds into the edge.
+        let straight = imageops::resize(&image_buffer, 4, 1, imageops::FilterType::Lanczos3);
+        assert!(straight.get_pixel(1, 0)[0] < 250 || straight.get_pixel(2, 0)[0] < 250);
+
+        let premultiplied = premultiply(&image_buffer, decode_channel);
+        let resized = unpremultiply(
+            &imageops::resize(&premultiplied, 4, 1, imageops::FilterType::Lanczos3),
+            encode_channel,
+        );
+
+        assert!(resized
+            .pixels()
+            .filter(|pixel| pixel[3] > 0)
+            .all(|pixel| pixel[0] >= 250));
+    }
+}
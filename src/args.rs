@@ -1,23 +1,107 @@
-use clap::{error::ErrorKind, CommandFactory, Parser};
-use dialoguer::console::style;
+use std::{ffi::OsString, path::PathBuf};
+
+use clap::{error::ErrorKind, CommandFactory, Error, Parser, Subcommand};
+use dialoguer::console::{style, Term};
+use image::Rgb;
 use itertools::all;
 
-use crate::validation::{percent_validator, ratio_validator};
+#[cfg(unix)]
+use crate::daemon::Request;
+use crate::{
+    collect::Layout,
+    hidpi::HiDpiScale,
+    physical::{PhysicalLength, DEFAULT_DPI},
+    planner::SizeSpec,
+    preview::Protocol,
+    resize::ResizeBackend,
+    snippet::Emit,
+    validation::{
+        color_validator, density_validator, directory_validator, dpi_validator, hidpi_validator,
+        megapixels_validator, percent_validator, physical_length_validator, pixels_validator,
+        ratio_validator, size_validator,
+    },
+};
+
+/// Flags defining the dimensions, which can't be mixed with the budget based
+/// flags (maximum byte size and pixel count).
+const DIMENSION_FLAGS: [&str; 8] = [
+    "height",
+    "width",
+    "height_percent",
+    "width_percent",
+    "height_physical",
+    "width_physical",
+    "ratio",
+    "ignore_aspect_ratio",
+];
+
+/// Subcommands of the command line interface.
+#[derive(Clone, Debug, Subcommand)]
+pub enum Command {
+    /// Resizes image files, directories and globs with the same flags, e.g.
+    /// `picst batch shots/*.png --width 800 --out resized/`.
+    #[command(disable_help_flag = true)]
+    Batch {
+        /// Paths, directories or globs of the images, followed by the flags.
+        /// See `picst batch --help`.
+        #[arg(
+            allow_hyphen_values = true,
+            required = true,
+            trailing_var_arg = true,
+            value_name = "PATHS AND FLAGS"
+        )]
+        words: Vec<String>,
+    },
+    /// Controls a running daemon.
+    #[cfg(unix)]
+    Ctl {
+        /// Path of the control socket [default: `$XDG_RUNTIME_DIR/picst.sock`,
+        /// or `picst-<uid>/picst.sock` in the temporary directory].
+        #[arg(long)]
+        socket: Option<PathBuf>,
+
+        /// Command sent to the daemon.
+        #[command(subcommand)]
+        request: Request,
+    },
+}
+
+#[allow(clippy::struct_excessive_bools)]
+/// Flags of the command line interface.
+#[derive(Clone, Parser, Debug)]
+#[command(args_conflicts_with_subcommands = true, author, version, about, long_about = None)]
+pub struct Args {
+    /// Subcommands, i.e. the batch processing and the control commands sent
+    /// to a running daemon.
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Size of the resized image as a single expression, in the style of the
+    /// `ImageMagick` geometry: a width (`800`, `800px` or `800x`), a height
+    /// (`x300`), a box to fit in (`800x600`), to fill (`800x600^`) or to match
+    /// exactly (`800x600!`), percentages (`50%` or `50x25%`) or a ratio
+    /// (`0.5x`, `2.0x` or `2/3`).
+    /// Replaces the other size flags.
+    #[arg(
+        conflicts_with_all = DIMENSION_FLAGS,
+        conflicts_with_all = ["maximum_byte_size", "megapixels", "max_pixels", "hidpi", "srcset_widths"],
+        long,
+        short,
+        value_parser = size_validator
+    )]
+    pub size: Option<SizeSpec>,
 
-#[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
-pub(crate) struct Args {
     /// Height of the resized image in pixels.
     /// Can be combined with `width` in pixels.
     /// Cannot be combined with `width` in percent.
     #[arg(conflicts_with = "ratio", long, short = 'H')]
-    pub(crate) height: Option<u32>,
+    pub height: Option<u32>,
 
     /// Width of the resized image in pixels.
     /// Can be combined with `height` in pixels.
     /// Cannot be combined with `height` in percent.
     #[arg(conflicts_with = "ratio", long, short)]
-    pub(crate) width: Option<u32>,
+    pub width: Option<u32>,
 
     /// Height of the resized image in percent.
     /// Can be combined with `width` in percent.
@@ -29,7 +113,7 @@ pub(crate) struct Args {
         long,
         value_parser = percent_validator
     )]
-    pub(crate) height_percent: Option<u32>,
+    pub height_percent: Option<u32>,
 
     /// Width of the resized image in percent.
     /// Can be combined with height in percent.
@@ -41,7 +125,136 @@ pub(crate) struct Args {
         long,
         value_parser = percent_validator
     )]
-    pub(crate) width_percent: Option<u32>,
+    pub width_percent: Option<u32>,
+
+    /// Height of the resized image as a physical length, e.g. `5cm`, `40mm`
+    /// or `2in`.
+    /// Converted to pixels with `dpi`.
+    /// Can be combined with `width` in pixels or as a physical length.
+    #[arg(
+        conflicts_with = "height",
+        conflicts_with = "height_percent",
+        conflicts_with = "width_percent",
+        conflicts_with = "ratio",
+        conflicts_with = "ignore_aspect_ratio",
+        long,
+        value_parser = physical_length_validator
+    )]
+    pub height_physical: Option<PhysicalLength>,
+
+    /// Width of the resized image as a physical length, e.g. `10cm`, `80mm`
+    /// or `4in`.
+    /// Converted to pixels with `dpi`.
+    /// Can be combined with `height` in pixels or as a physical length.
+    #[arg(
+        conflicts_with = "width",
+        conflicts_with = "height_percent",
+        conflicts_with = "width_percent",
+        conflicts_with = "ratio",
+        conflicts_with = "ignore_aspect_ratio",
+        long,
+        value_parser = physical_length_validator
+    )]
+    pub width_physical: Option<PhysicalLength>,
+
+    /// Dots per inch used to convert physical lengths and stored as density
+    /// metadata in the saved files [default: 96].
+    #[arg(long, value_parser = dpi_validator)]
+    pub dpi: Option<u16>,
+
+    /// Flattens transparent images onto a background color, e.g. `#ffffff`,
+    /// for applications ignoring the alpha channel.
+    #[arg(long, value_parser = color_validator)]
+    pub flatten: Option<Rgb<u8>>,
+
+    /// Drops the alpha channel, making transparent images fully opaque.
+    #[arg(conflicts_with = "flatten", long)]
+    pub strip_alpha: bool,
+
+    /// Resamples the image in linear light rather than on the sRGB encoded
+    /// values, which keeps fine patterns and thin text from darkening.
+    /// Slower, but more accurate.
+    #[arg(long)]
+    pub linear: bool,
+
+    /// Backend used to resize the images.
+    /// The `fast` one, using SIMD instructions on all the cores, requires the
+    /// `fast-resize` feature.
+    /// Note: linear light resampling and high DPI downsampling always use the
+    /// `image` one, and the `fast` one premultiplies the alpha channel at
+    /// 8-bit precision instead of float precision.
+    #[arg(default_value_t, long, value_enum)]
+    pub backend: ResizeBackend,
+
+    /// Directory where the resized images are saved as PNG files, in addition
+    /// to the clipboard.
+    #[arg(long)]
+    pub output_dir: Option<PathBuf>,
+
+    /// Moves a text snippet embedding the resized image to the clipboard
+    /// instead of the image data, either a data URI, a Markdown image or an
+    /// HTML image element.
+    #[arg(long, value_enum)]
+    pub emit: Option<Emit>,
+
+    /// Widths in pixels of the responsive variants, e.g. `320,640,1280`,
+    /// replacing the size flags.
+    /// The variants are saved with the timestamp of the image and their
+    /// descriptor, e.g. `picst-1697040000000-640w.png`, and an HTML image
+    /// element referencing them in its `srcset` attribute is moved to the
+    /// clipboard.
+    #[arg(
+        conflicts_with_all = DIMENSION_FLAGS,
+        conflicts_with_all = ["maximum_byte_size", "megapixels", "max_pixels", "hidpi", "emit"],
+        long,
+        value_delimiter = ',',
+        value_parser = pixels_validator
+    )]
+    pub srcset_widths: Option<Vec<u32>>,
+
+    /// Pixel densities of the responsive variants, e.g. `1,2,3`, the size
+    /// flags giving the `1x` dimensions.
+    /// Saved and referenced like the `srcset_widths` ones, e.g.
+    /// `picst-1697040000000-2x.png`.
+    #[arg(
+        conflicts_with_all = ["srcset_widths", "emit"],
+        long,
+        value_delimiter = ',',
+        value_parser = density_validator
+    )]
+    pub srcset_densities: Option<Vec<u32>>,
+
+    /// Collects the copied images instead of resizing them, until the count
+    /// is reached, e.g. `--collect 3`, or until the stitch is requested with
+    /// the `Enter` key or `picst ctl stitch`.
+    /// The collected images are then stitched into a single one, which is
+    /// resized.
+    #[arg(
+        default_missing_value = "0",
+        long,
+        num_args = 0..=1,
+        value_name = "COUNT"
+    )]
+    pub collect: Option<u32>,
+
+    /// Layout of the stitched images.
+    #[arg(default_value_t, long, requires = "collect", value_enum)]
+    pub layout: Layout,
+
+    /// Spacing in pixels between the stitched images.
+    #[arg(default_value_t = 0, long, requires = "collect")]
+    pub spacing: u32,
+
+    /// Background color of the stitched images, e.g. `#ffffff`, transparent
+    /// by default.
+    #[arg(long, requires = "collect", value_parser = color_validator)]
+    pub background: Option<Rgb<u8>>,
+
+    /// Writes the resized copies of the image files copied from a file
+    /// manager, or found in the watched directory, next to the originals, e.g.
+    /// `shot-picst.png` for `shot.jpg`.
+    #[arg(long)]
+    pub next_to_originals: bool,
 
     /// Ratio to resize the image.
     #[arg(
@@ -49,26 +262,161 @@ pub(crate) struct Args {
         conflicts_with = "ignore_aspect_ratio",
         value_parser = ratio_validator
     )]
-    pub(crate) ratio: Option<f32>,
+    pub ratio: Option<f32>,
 
     /// Ignore aspect ratio.
     #[arg(conflicts_with = "ratio", long)]
-    pub(crate) ignore_aspect_ratio: bool,
+    pub ignore_aspect_ratio: bool,
 
     /// Maximum byte size.
-    #[arg(exclusive = true, long)]
-    pub(crate) maximum_byte_size: Option<u32>,
+    #[arg(conflicts_with_all = DIMENSION_FLAGS, long)]
+    pub maximum_byte_size: Option<u32>,
+
+    /// Target pixel count in megapixels, e.g. `2.0`.
+    /// The image is scaled uniformly so that its width × height gets as close
+    /// as possible to the target without exceeding it.
+    #[arg(
+        conflicts_with_all = DIMENSION_FLAGS,
+        conflicts_with = "maximum_byte_size",
+        long,
+        value_parser = megapixels_validator
+    )]
+    pub megapixels: Option<f32>,
+
+    /// Target pixel count, e.g. `2000000`.
+    /// Same as `megapixels` but expressed in pixels.
+    #[arg(
+        conflicts_with_all = DIMENSION_FLAGS,
+        conflicts_with = "maximum_byte_size",
+        conflicts_with = "megapixels",
+        long,
+        value_parser = pixels_validator
+    )]
+    pub max_pixels: Option<u32>,
+
+    /// Normalizes screenshots taken on high DPI displays by dividing both
+    /// dimensions by the scale factor, either `auto`, `2` or `3`.
+    /// With `auto`, the factor is read from `GDK_SCALE` or `QT_SCALE_FACTOR`
+    /// on Linux desktops, or taken from the `PICST_HIDPI_SCALE` environment
+    /// variable: the scaling of macOS, Windows and the Wayland compositors
+    /// isn't detected.
+    #[arg(
+        conflicts_with_all = DIMENSION_FLAGS,
+        conflicts_with = "maximum_byte_size",
+        conflicts_with = "megapixels",
+        conflicts_with = "max_pixels",
+        long,
+        value_parser = hidpi_validator
+    )]
+    pub hidpi: Option<HiDpiScale>,
+
+    /// Only processes the images at least as wide, in pixels, the other ones
+    /// being left untouched, e.g. to ignore the copied icons.
+    #[arg(long, value_parser = pixels_validator)]
+    pub min_width: Option<u32>,
+
+    /// Only processes the images at least as tall, in pixels, the other ones
+    /// being left untouched.
+    #[arg(long, value_parser = pixels_validator)]
+    pub min_height: Option<u32>,
+
+    /// Only processes the images weighing at least as many bytes once
+    /// decoded, the other ones being left untouched.
+    #[arg(long)]
+    pub min_bytes: Option<u32>,
+
+    /// Only processes the landscape images, i.e. wider than tall, the other
+    /// ones being left untouched.
+    #[arg(long)]
+    pub only_landscape: bool,
+
+    /// Directory watched for new image files, e.g. the one where a screenshot
+    /// tool saves them, processed along with the clipboard content.
+    /// Note: the files already in the directory are ignored.
+    #[arg(long, value_parser = directory_validator)]
+    pub watch_dir: Option<PathBuf>,
+
+    /// Only processes the new files of the watched directory, ignoring the
+    /// clipboard content.
+    #[arg(long, requires = "watch_dir")]
+    pub watch_only: bool,
+
+    /// Previews the resized image in the terminal and asks for a confirmation
+    /// before moving it to the clipboard, with the kitty graphics protocol,
+    /// sixel graphics or Unicode half blocks, detected by default.
+    /// The dimensions can be re-edited, or the image left untouched.
+    /// Note: nothing is previewed without a terminal to prompt on.
+    #[arg(
+        conflicts_with_all = ["srcset_widths", "srcset_densities"],
+        default_missing_value = "auto",
+        long,
+        num_args = 0..=1,
+        value_enum,
+        value_name = "PROTOCOL"
+    )]
+    pub preview: Option<Protocol>,
+
+    /// Never prompts: the missing values are taken from the flags of the
+    /// `PICST_DEFAULT_FLAGS` environment variable, e.g. `--width-percent 50`,
+    /// or the image is skipped.
+    /// Implied when there's no terminal to prompt on.
+    #[arg(long)]
+    pub no_input: bool,
+
+    /// Shows a full-screen dashboard with the settings, the history of the
+    /// processed images and the errors, instead of printing them.
+    /// Requires the `tui` feature.
+    /// Note: nothing is prompted, the presets of the dashboard replacing the
+    /// wizard.
+    #[cfg(feature = "tui")]
+    #[arg(long)]
+    pub tui: bool,
+
+    /// Listens for the commands sent with `picst ctl` on a Unix domain
+    /// socket, to change the flags, pause or undo without restarting.
+    #[cfg(unix)]
+    #[arg(long)]
+    pub daemon: bool,
+
+    /// Path of the control socket [default: `$XDG_RUNTIME_DIR/picst.sock`,
+    /// or `picst-<uid>/picst.sock` in the temporary directory].
+    #[cfg(unix)]
+    #[arg(long, requires = "daemon")]
+    pub socket: Option<PathBuf>,
 }
 
 impl Args {
     /// Custom parser with some additional checking.
-    pub(crate) fn custom_parse() -> Args {
-        let args = Args::parse();
+    /// Note: exits on errors, printing them.
+    #[must_use]
+    pub fn custom_parse() -> Args {
+        Args::try_custom_parse_from(std::env::args_os())
+            .and_then(Args::check_stitch_trigger)
+            .unwrap_or_else(|error| error.exit())
+    }
+
+    /// Same as `custom_parse`, from the given arguments and without exiting
+    /// on errors.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the arguments are invalid or conflicting.
+    pub fn try_custom_parse_from<I, T>(iter: I) -> Result<Args, Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        Args::try_parse_from(iter)?.check_conflicts()
+    }
+
+    /// Checks the conflicts which can't be expressed with the attributes.
+    pub(crate) fn check_conflicts(self) -> Result<Args, Error> {
+        let args = self;
         let mut cmd = Args::command();
 
         // The `ignore_aspect_ratio` flag can be mixed with `height` and `width`.
         if (args.height.is_some() && args.width.is_some()) && args.ignore_aspect_ratio {
-            cmd.error(
+            return Err(cmd.error(
                 ErrorKind::ArgumentConflict,
                 // Note: there's no coloring API exposed with Clap.
                 // https://github.com/clap-rs/clap/issues/2035
@@ -78,15 +426,14 @@ impl Args {
                     style("--height").yellow(),
                     style("--width").yellow()
                 ),
-            )
-            .exit();
+            ));
         }
 
         // The `ignore_aspect_ratio` flag can be mixed with `height-percent` and `width-percent`.
         if (args.height_percent.is_some() && args.height_percent.is_some())
             && args.ignore_aspect_ratio
         {
-            cmd.error(
+            return Err(cmd.error(
                 ErrorKind::ArgumentConflict,
                 format!(
                     "The argument '{}' cannot be used with '{}' and '{}'",
@@ -94,22 +441,179 @@ impl Args {
                     style("--height-percent").yellow(),
                     style("--width-percent").yellow()
                 ),
-            )
-            .exit();
+            ));
+        }
+
+        Ok(args)
+    }
+
+    /// Checks that the collected images can be stitched without a count, i.e.
+    /// with the `Enter` key or `picst ctl stitch`.
+    /// Note: the terminal is only checked for the command line, the daemon
+    /// being the trigger of the flags it receives.
+    pub(crate) fn check_stitch_trigger(self) -> Result<Args, Error> {
+        if self.collect == Some(0) && !self.has_key_controls() && !self.is_daemon() {
+            return Err(Args::command().error(
+                ErrorKind::MissingRequiredArgument,
+                format!(
+                    "The argument '{}' needs a count without a terminal or '{}', e.g. '{}'",
+                    style("--collect").yellow(),
+                    style("--daemon").yellow(),
+                    style("--collect 3").yellow()
+                ),
+            ));
+        }
+
+        Ok(self)
+    }
+
+    /// Checks if the user can be prompted for the missing values.
+    #[must_use]
+    pub fn is_interactive(&self) -> bool {
+        !self.no_input && !self.is_tui() && Term::stderr().is_term()
+    }
+
+    /// Checks if the dashboard is requested, which requires the `tui`
+    /// feature.
+    #[must_use]
+    pub fn is_tui(&self) -> bool {
+        #[cfg(feature = "tui")]
+        return self.tui;
+
+        #[cfg(not(feature = "tui"))]
+        false
+    }
+
+    /// Checks if the control socket is requested, which is only supported on
+    /// Unix.
+    #[must_use]
+    pub fn is_daemon(&self) -> bool {
+        #[cfg(unix)]
+        return self.daemon;
+
+        #[cfg(not(unix))]
+        false
+    }
+
+    /// Checks if the single key controls can be read from the terminal,
+    /// which is only supported on Unix.
+    #[must_use]
+    pub fn has_key_controls(&self) -> bool {
+        cfg!(unix) && self.is_interactive()
+    }
+
+    /// Returns the dots per inch, falling back to the default one.
+    #[must_use]
+    pub fn get_dpi(&self) -> u16 {
+        self.dpi.unwrap_or(DEFAULT_DPI)
+    }
+
+    /// Replaces the size flags with the size specification, e.g. the one
+    /// picked with the settings wizard, and returns the flags describing it.
+    pub(crate) fn set_size_spec(&mut self, size_spec: SizeSpec) -> Vec<String> {
+        self.size = None;
+        self.height = None;
+        self.width = None;
+        self.height_percent = None;
+        self.width_percent = None;
+        self.height_physical = None;
+        self.width_physical = None;
+        self.ratio = None;
+        self.ignore_aspect_ratio = false;
+        self.maximum_byte_size = None;
+        self.megapixels = None;
+        self.max_pixels = None;
+        self.hidpi = None;
+        self.srcset_widths = None;
+
+        let mut flags = Vec::new();
+        let mut push_flag = |flag: &str, value: String| {
+            flags.push(format!("--{flag}"));
+            flags.push(value);
+        };
+
+        match size_spec {
+            SizeSpec::Pixels(height, width) => {
+                self.height = height;
+                self.width = width;
+                if let Some(height) = height {
+                    push_flag("height", height.to_string());
+                }
+                if let Some(width) = width {
+                    push_flag("width", width.to_string());
+                }
+            }
+            // The density is kept along with the converted dimensions.
+            SizeSpec::Physical(height, width, dpi) => {
+                self.height = height;
+                self.width = width;
+                self.dpi = Some(dpi);
+                if let Some(height) = height {
+                    push_flag("height", height.to_string());
+                }
+                if let Some(width) = width {
+                    push_flag("width", width.to_string());
+                }
+                push_flag("dpi", dpi.to_string());
+            }
+            SizeSpec::Percent(height, width) => {
+                self.height_percent = height;
+                self.width_percent = width;
+                if let Some(height) = height {
+                    push_flag("height-percent", height.to_string());
+                }
+                if let Some(width) = width {
+                    push_flag("width-percent", width.to_string());
+                }
+            }
+            SizeSpec::Ratio(ratio) => {
+                self.ratio = Some(ratio);
+                push_flag("ratio", ratio.to_string());
+            }
+            SizeSpec::PixelCount(budget) => {
+                let max_pixels = u32::try_from(budget).unwrap_or(u32::MAX);
+
+                self.max_pixels = Some(max_pixels);
+                push_flag("max-pixels", max_pixels.to_string());
+            }
+            SizeSpec::MaxByteSize { maximum, .. } => {
+                let maximum_byte_size = u32::try_from(maximum).unwrap_or(u32::MAX);
+
+                self.maximum_byte_size = Some(maximum_byte_size);
+                push_flag("maximum-byte-size", maximum_byte_size.to_string());
+            }
+            SizeSpec::HiDpi(factor) => {
+                self.hidpi = Some(HiDpiScale::Factor(factor));
+                push_flag("hidpi", factor.to_string());
+            }
+            // Only the size expression describes the boxes.
+            SizeSpec::Fit(height, width) => {
+                self.size = Some(size_spec);
+                push_flag("size", format!("{width}x{height}"));
+            }
+            SizeSpec::Fill(height, width) => {
+                self.size = Some(size_spec);
+                push_flag("size", format!("{width}x{height}^"));
+            }
         }
 
-        args
+        flags
     }
 }
 
+/// Metadata of the dimensions variant.
 #[derive(Debug, PartialEq)]
-pub(crate) struct ArgsMetadata {
-    pub(crate) is_pixel: bool,
-    pub(crate) ignore_aspect_ratio: bool,
+pub struct ArgsMetadata {
+    /// Whether the dimensions are in pixels rather than in percent.
+    pub is_pixel: bool,
+    /// Whether the aspect ratio is ignored.
+    pub ignore_aspect_ratio: bool,
 }
 
 impl ArgsMetadata {
-    fn new(is_pixel: bool, ignore_aspect_ratio: bool) -> Self {
+    /// Creates the metadata of the dimensions variant.
+    #[must_use]
+    pub fn new(is_pixel: bool, ignore_aspect_ratio: bool) -> Self {
         Self {
             is_pixel,
             ignore_aspect_ratio,
@@ -117,9 +621,12 @@ impl ArgsMetadata {
     }
 }
 
-/// Result of parsing the arguments as an enumeration.
+/// Result of parsing the arguments as an enumeration, i.e. the size
+/// specification.
 #[derive(Debug, PartialEq)]
-pub(crate) enum ArgsResult {
+pub enum ArgsResult {
+    /// High DPI variant.
+    HiDpi(HiDpiScale),
     /// Dimensions variant as a tuple of (height, width, dimensions in pixels,
     /// ignore aspect ratio).
     Dimensions(Option<u32>, Option<u32>, ArgsMetadata),
@@ -127,17 +634,44 @@ pub(crate) enum ArgsResult {
     MaxByteSize(u32),
     /// No flags variant.
     NoFlags,
+    /// Pixel count variant, as a budget of pixels.
+    PixelCount(u64),
     /// Ratio variant.
     Ratio(f32),
+    /// Box to fit in variant, as a tuple of (height, width) in pixels.
+    Fit(u32, u32),
+    /// Box to fill variant, as a tuple of (height, width) in pixels.
+    Fill(u32, u32),
 }
 
 impl ArgsResult {
-    pub(crate) fn get(args: &Args) -> Self {
+    /// Returns the size specification described by the arguments.
+    #[must_use]
+    pub fn get(args: &Args) -> Self {
+        // The size expression replaces all the other size flags.
+        if let Some(size_spec) = args.size {
+            return ArgsResult::from_size_spec(size_spec);
+        }
+
         // The maximum byte size is exclusive, check it first.
         if let Some(maximum_byte_size) = args.maximum_byte_size {
             return ArgsResult::MaxByteSize(maximum_byte_size);
         }
 
+        // Same for the pixel count, either in megapixels or in pixels.
+        if let Some(megapixels) = args.megapixels {
+            return ArgsResult::PixelCount((f64::from(megapixels) * 1_000_000.).round() as u64);
+        }
+
+        if let Some(max_pixels) = args.max_pixels {
+            return ArgsResult::PixelCount(u64::from(max_pixels));
+        }
+
+        // Same for the HiDPI scale.
+        if let Some(scale) = args.hidpi {
+            return ArgsResult::HiDpi(scale);
+        }
+
         // Check if the ratio is provided and return the corresponding variant.
         if let Some(ratio) = args.ratio {
             return ArgsResult::Ratio(ratio);
@@ -152,24 +686,35 @@ impl ArgsResult {
                 args.width_percent,
             ],
             |arg| arg.is_none(),
-        ) && args.ratio.is_none()
+        ) && args.height_physical.is_none()
+            && args.width_physical.is_none()
+            && args.ratio.is_none()
         {
             return ArgsResult::NoFlags;
         }
 
-        let has_pixel_height = args.height.is_some();
-        let has_pixel_width = args.width.is_some();
+        // Physical lengths are converted to pixels upfront.
+        let dpi = args.get_dpi();
+        let height_pixels = args
+            .height
+            .or_else(|| args.height_physical.map(|length| length.to_pixels(dpi)));
+        let width_pixels = args
+            .width
+            .or_else(|| args.width_physical.map(|length| length.to_pixels(dpi)));
+
+        let has_pixel_height = height_pixels.is_some();
+        let has_pixel_width = width_pixels.is_some();
 
         // Re-map the height.
         let height = if has_pixel_height {
-            args.height
+            height_pixels
         } else {
             args.height_percent
         };
 
         // Re-map the width.
         let width = if has_pixel_width {
-            args.width
+            width_pixels
         } else {
             args.width_percent
         };
@@ -184,6 +729,33 @@ impl ArgsResult {
             ),
         )
     }
+
+    /// Returns the variant matching the intent of a size expression, both
+    /// dimensions ignoring the aspect ratio.
+    fn from_size_spec(size_spec: SizeSpec) -> Self {
+        match size_spec {
+            SizeSpec::Pixels(height, width) | SizeSpec::Physical(height, width, _) => {
+                ArgsResult::Dimensions(
+                    height,
+                    width,
+                    ArgsMetadata::new(true, height.is_some() && width.is_some()),
+                )
+            }
+            SizeSpec::Percent(height, width) => ArgsResult::Dimensions(
+                height,
+                width,
+                ArgsMetadata::new(false, height.is_some() && width.is_some()),
+            ),
+            SizeSpec::Ratio(ratio) => ArgsResult::Ratio(ratio),
+            SizeSpec::PixelCount(budget) => ArgsResult::PixelCount(budget),
+            SizeSpec::MaxByteSize { maximum, .. } => {
+                ArgsResult::MaxByteSize(u32::try_from(maximum).unwrap_or(u32::MAX))
+            }
+            SizeSpec::HiDpi(factor) => ArgsResult::HiDpi(HiDpiScale::Factor(factor)),
+            SizeSpec::Fit(height, width) => ArgsResult::Fit(height, width),
+            SizeSpec::Fill(height, width) => ArgsResult::Fill(height, width),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -191,9 +763,10 @@ mod tests {
     use clap::{CommandFactory, Parser};
 
     use super::{Args, ArgsMetadata, ArgsResult};
+    use crate::hidpi::HiDpiScale;
 
     fn get_args_result(flags: &str) -> ArgsResult {
-        let args = Args::parse_from(format!("picst {}", flags).split_whitespace());
+        let args = Args::parse_from(format!("picst {flags}").split_whitespace());
 
         ArgsResult::get(&args)
     }
@@ -201,7 +774,22 @@ mod tests {
     #[test]
     /// This is a global test to verify that parsing the arguments doesn't break.
     fn check_args() {
-        Args::command().debug_assert()
+        Args::command().debug_assert();
+    }
+
+    #[test]
+    fn check_stitch_trigger() {
+        let check = |flags: &str| {
+            Args::parse_from(format!("picst --no-input {flags}").split_whitespace())
+                .check_stitch_trigger()
+        };
+
+        assert!(check("--collect").is_err());
+        assert!(check("--collect 0").is_err());
+        assert!(check("--collect 3").is_ok());
+        assert!(check("").is_ok());
+        #[cfg(unix)]
+        assert!(check("--collect --daemon").is_ok());
     }
 
     #[test]
@@ -289,8 +877,97 @@ mod tests {
         );
     }
 
+    #[test]
+    fn check_args_result_full_dimensions_physical() {
+        assert_eq!(
+            get_args_result("--height-physical 1in --width-physical 2.54cm --dpi 300"),
+            ArgsResult::Dimensions(Some(300), Some(300), ArgsMetadata::new(true, false))
+        );
+    }
+
+    #[test]
+    fn check_args_result_width_only_physical_default_dpi() {
+        assert_eq!(
+            get_args_result("--width-physical 50.8mm"),
+            ArgsResult::Dimensions(None, Some(192), ArgsMetadata::new(true, false))
+        );
+    }
+
+    #[test]
+    fn check_args_result_physical_and_pixels() {
+        assert_eq!(
+            get_args_result("--height 10 --width-physical 1in --dpi 150"),
+            ArgsResult::Dimensions(Some(10), Some(150), ArgsMetadata::new(true, false))
+        );
+    }
+
+    #[test]
+    fn check_args_result_megapixels() {
+        assert_eq!(
+            get_args_result("--megapixels 2.5"),
+            ArgsResult::PixelCount(2_500_000)
+        );
+    }
+
+    #[test]
+    fn check_args_result_max_pixels() {
+        assert_eq!(
+            get_args_result("--max-pixels 1000"),
+            ArgsResult::PixelCount(1000)
+        );
+    }
+
+    #[test]
+    fn check_args_result_max_byte_size() {
+        assert_eq!(
+            get_args_result("--maximum-byte-size 1000 --dpi 300"),
+            ArgsResult::MaxByteSize(1000)
+        );
+    }
+
+    #[test]
+    fn check_args_result_hidpi() {
+        assert_eq!(
+            get_args_result("--hidpi auto"),
+            ArgsResult::HiDpi(HiDpiScale::Auto)
+        );
+        assert_eq!(
+            get_args_result("--hidpi 2"),
+            ArgsResult::HiDpi(HiDpiScale::Factor(2))
+        );
+    }
+
     #[test]
     fn check_args_result_ratio() {
         assert_eq!(get_args_result("--ratio 0.7"), ArgsResult::Ratio(0.7));
     }
+
+    #[test]
+    fn check_args_result_size() {
+        assert_eq!(
+            get_args_result("--size 800"),
+            ArgsResult::Dimensions(None, Some(800), ArgsMetadata::new(true, false))
+        );
+        assert_eq!(
+            get_args_result("-s 800x600!"),
+            ArgsResult::Dimensions(Some(600), Some(800), ArgsMetadata::new(true, true))
+        );
+        assert_eq!(
+            get_args_result("--size 50%"),
+            ArgsResult::Dimensions(None, Some(50), ArgsMetadata::new(false, false))
+        );
+        assert_eq!(get_args_result("--size 2/5"), ArgsResult::Ratio(0.4));
+        assert_eq!(get_args_result("--size 800x600"), ArgsResult::Fit(600, 800));
+        assert_eq!(
+            get_args_result("--size 800x600^"),
+            ArgsResult::Fill(600, 800)
+        );
+
+        // The size expression replaces the other size flags.
+        for flags in ["--width 800", "--ratio 0.5", "--megapixels 2"] {
+            let args = format!("picst --size 800 {flags}");
+
+            assert!(Args::try_parse_from(args.split_whitespace()).is_err());
+        }
+    }
 }
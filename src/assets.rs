@@ -1,23 +1,29 @@
 use dialoguer::console::Emoji;
 
 /// Banner.
-pub(crate) static PICST: &str = r#"
+pub static PICST: &str = r"
 ██████╗ ██╗ ██████╗███████╗████████╗
 ██╔══██╗██║██╔════╝██╔════╝╚══██╔══╝
 ██████╔╝██║██║     ███████╗   ██║   
 ██╔═══╝ ██║██║     ╚════██║   ██║   
 ██║     ██║╚██████╗███████║   ██║   
 ╚═╝     ╚═╝ ╚═════╝╚══════╝   ╚═╝  
-"#;
+";
 
 /// Spinner animation.
 pub(crate) static DOTS: &[&str; 14] = &[
     "⠄", "⠆", "⠇", "⠋", "⠙", "⠸", "⠰", "⠠", "⠰", "⠸", "⠙", "⠋", "⠇", "⠆",
 ];
 
-pub(crate) static BOOM: Emoji = Emoji("💥 ", "");
+/// Emoji printed when listening for control commands.
+pub static ANTENNA: Emoji = Emoji("📡 ", "");
+/// Emoji printed along with the errors.
+pub static BOOM: Emoji = Emoji("💥 ", "");
 pub(crate) static CLIPBOARD: Emoji = Emoji("📋 ", "");
+pub(crate) static COLLECT: Emoji = Emoji("🧩 ", "");
+pub(crate) static FLOPPY: Emoji = Emoji("💾 ", "");
 pub(crate) static HEIGHT: Emoji = Emoji("↕️ ", "");
+pub(crate) static KEYBOARD: Emoji = Emoji("⌨️  ", "");
 pub(crate) static STATS: Emoji = Emoji("📊 ", "");
 pub(crate) static WIDTH: Emoji = Emoji("↔️ ", "");
 pub(crate) static ZAP: Emoji = Emoji("⚡", "");
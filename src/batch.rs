@@ -0,0 +1,574 @@
+use std::{
+    collections::{hash_map::Entry, HashMap, HashSet},
+    env, fs,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+    time::Instant,
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use clap::{error::ErrorKind, Arg, CommandFactory, Error, FromArgMatches};
+use dialoguer::console::style;
+use glob::glob;
+use image::ImageFormat;
+use indicatif::{BinaryBytes, HumanDuration};
+
+use crate::{
+    args::{Args, ArgsResult},
+    assets::{BOOM, FLOPPY, STATS, ZAP},
+    encoder::save,
+    file_list::get_resized_path,
+    filter::apply_filters,
+    resize::ResizeOptions,
+    skip::Skip,
+    spinner::display_progress_bar,
+    variants::{get_variant_path, Targets, Variants},
+    wizard::DEFAULT_FLAGS_CONFIG,
+};
+
+/// Characters turning a path into a glob pattern.
+static GLOB_CHARACTERS: &[char; 3] = &['*', '?', '['];
+
+/// Flags of the batch subcommand, i.e. the regular flags along with the files
+/// to resize and the output directory.
+#[derive(Debug)]
+pub struct BatchArgs {
+    /// Flags applied to all the files.
+    pub args: Args,
+    /// Paths, directories or globs of the images.
+    pub paths: Vec<String>,
+    /// Directory where the resized images are saved, next to the originals
+    /// if not set.
+    pub out: Option<PathBuf>,
+}
+
+impl BatchArgs {
+    /// Parses the words following `picst batch`, exiting on errors.
+    #[must_use]
+    pub fn parse_from(words: &[String]) -> Self {
+        Self::try_parse_from(words).unwrap_or_else(|error| error.exit())
+    }
+
+    /// Same as `parse_from`, without exiting on errors.
+    /// Note: the images are never prompted for, so some size flags are needed
+    /// unless `PICST_DEFAULT_FLAGS` is set.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the flags are invalid, conflicting, missing or only relevant
+    /// to the clipboard.
+    pub fn try_parse_from(words: &[String]) -> Result<Self, Error> {
+        let mut cmd = Args::command()
+            .name("picst batch")
+            .bin_name("picst batch")
+            .about("Resizes image files, directories and globs with the same flags.")
+            .disable_help_subcommand(true)
+            .arg(
+                Arg::new("paths")
+                    .help("Paths, directories or globs of the images.")
+                    .num_args(1..)
+                    .required(true)
+                    .value_name("PATHS"),
+            )
+            .arg(
+                Arg::new("out")
+                    .help("Directory where the resized images are saved, next to the originals if not set.")
+                    .long("out")
+                    .short('o')
+                    .value_parser(clap::value_parser!(PathBuf)),
+            );
+
+        // The subcommands only make sense on their own.
+        let names = cmd
+            .get_subcommands()
+            .map(|subcommand| subcommand.get_name().to_owned())
+            .collect::<Vec<_>>();
+        for name in names {
+            cmd = cmd.mut_subcommand(name, |subcommand| subcommand.hide(true));
+        }
+
+        let mut matches = cmd.try_get_matches_from_mut(
+            std::iter::once(String::from("picst batch")).chain(words.iter().cloned()),
+        )?;
+        let paths = matches
+            .remove_many::<String>("paths")
+            .map(Iterator::collect)
+            .unwrap_or_default();
+        let out = matches.remove_one::<PathBuf>("out");
+        let mut args = Args::from_arg_matches(&matches)?.check_conflicts()?;
+
+        // The clipboard flags are rejected rather than silently ignored.
+        #[cfg(unix)]
+        let is_daemon = args.daemon;
+        #[cfg(not(unix))]
+        let is_daemon = false;
+        let clipboard_flags = [
+            (args.collect.is_some(), "--collect"),
+            (is_daemon, "--daemon"),
+            (args.emit.is_some(), "--emit"),
+            (args.next_to_originals, "--next-to-originals"),
+            (args.output_dir.is_some(), "--output-dir"),
+            (args.preview.is_some(), "--preview"),
+            (args.is_tui(), "--tui"),
+            (args.watch_dir.is_some(), "--watch-dir"),
+        ];
+        if let Some((_, flag)) = clipboard_flags.iter().find(|(is_set, _)| *is_set) {
+            return Err(cmd.error(
+                ErrorKind::ArgumentConflict,
+                format!(
+                    "The argument '{}' cannot be used with '{}'",
+                    style(flag).yellow(),
+                    style("picst batch").yellow()
+                ),
+            ));
+        }
+
+        // There's no one to prompt for the missing values.
+        if ArgsResult::get(&args) == ArgsResult::NoFlags
+            && Variants::from_args(&args).is_none()
+            && env::var_os(DEFAULT_FLAGS_CONFIG).is_none()
+        {
+            return Err(cmd.error(
+                ErrorKind::MissingRequiredArgument,
+                format!(
+                    "Some size flags are required, e.g. '{}', or set `{DEFAULT_FLAGS_CONFIG}`",
+                    style("--width 800").yellow()
+                ),
+            ));
+        }
+
+        args.no_input = true;
+
+        Ok(Self { args, paths, out })
+    }
+}
+
+/// Expands the paths, directories and globs into the list of image files,
+/// along with the ones which can't be expanded or would be saved to the same
+/// path as a previous one, and the reason why.
+/// Note: the directories aren't walked recursively, and only the image files
+/// are kept from them and from the globs.
+fn expand_paths(paths: &[String], out: Option<&Path>) -> (Vec<PathBuf>, Vec<(String, String)>) {
+    let mut files = Vec::new();
+    let mut failures = Vec::new();
+    let mut seen_files = HashSet::new();
+    let mut output_paths = HashMap::<PathBuf, PathBuf>::new();
+
+    let is_image = |path: &Path| path.is_file() && ImageFormat::from_path(path).is_ok();
+
+    for path in paths {
+        let expanded = if Path::new(path).is_dir() {
+            fs::read_dir(path)
+                .map(|entries| {
+                    let mut entries = entries
+                        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                        .filter(|path| is_image(path))
+                        .collect::<Vec<_>>();
+
+                    entries.sort();
+
+                    entries
+                })
+                .map_err(|error| error.to_string())
+        } else if path.contains(GLOB_CHARACTERS) {
+            glob(path)
+                .map(|entries| {
+                    entries
+                        .filter_map(Result::ok)
+                        .filter(|path| is_image(path))
+                        .collect()
+                })
+                .map_err(|error| error.to_string())
+        } else {
+            // Plain files are kept as is, the loading reporting any issue.
+            Ok(vec![PathBuf::from(path)])
+        };
+
+        match expanded {
+            Ok(expanded) if expanded.is_empty() => {
+                failures.push((path.clone(), String::from("no image files found")));
+            }
+            Ok(expanded) => {
+                for file in expanded {
+                    if !seen_files.insert(file.clone()) {
+                        continue;
+                    }
+
+                    // Files with the same stem, from other directories or in
+                    // other formats, would overwrite each other.
+                    match output_paths.entry(get_output_path(&file, out)) {
+                        Entry::Occupied(entry) => failures.push((
+                            file.display().to_string(),
+                            format!(
+                                "would be saved to {}, like {}",
+                                entry.key().display(),
+                                entry.get().display()
+                            ),
+                        )),
+                        Entry::Vacant(entry) => {
+                            entry.insert(file.clone());
+                            files.push(file);
+                        }
+                    }
+                }
+            }
+            Err(reason) => failures.push((path.clone(), reason)),
+        }
+    }
+
+    (files, failures)
+}
+
+/// Returns the path of the resized copy of a file, in the output directory
+/// with the same name if set, e.g. `out/shot.jpg`, or next to the original
+/// otherwise, e.g. `shot-picst.png`.
+/// Note: the formats which can't be encoded are saved as PNG files, and the
+/// originals are never overwritten.
+fn get_output_path(path: &Path, out: Option<&Path>) -> PathBuf {
+    let Some(out) = out else {
+        return get_resized_path(path);
+    };
+
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy())
+        .unwrap_or_default();
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .filter(|extension| {
+            ["jpg", "jpeg", "png"]
+                .iter()
+                .any(|supported| extension.eq_ignore_ascii_case(supported))
+        })
+        .unwrap_or("png");
+    let output_path = out.join(format!("{stem}.{extension}"));
+
+    let is_original = match (fs::canonicalize(&output_path), fs::canonicalize(path)) {
+        (Ok(output_path), Ok(path)) => output_path == path,
+        _ => false,
+    };
+
+    if is_original {
+        get_resized_path(&output_path)
+    } else {
+        output_path
+    }
+}
+
+/// Summary of a resized file.
+#[derive(Debug)]
+struct FileSummary {
+    /// Original dimensions, as a tuple of (width, height).
+    original_dimensions: (u32, u32),
+    /// Size of the original file in bytes.
+    original_size: u64,
+    /// Resized dimensions, as a tuple of (width, height).
+    resized_dimensions: (u32, u32),
+    /// Size of the resized file in bytes.
+    resized_size: u64,
+    /// Saved files, the main one being the last.
+    saved_paths: Vec<PathBuf>,
+}
+
+/// Loads, resizes and saves a file.
+fn process_file(
+    args: &Args,
+    options: &ResizeOptions,
+    path: &Path,
+    out: Option<&Path>,
+) -> Result<FileSummary> {
+    let original_size = fs::metadata(path)?.len();
+    let image_buffer = image::open(path)
+        .map_err(|error| anyhow!("can't be loaded, {error}"))?
+        .to_rgba8();
+    let image_buffer = apply_filters(args, image_buffer)?;
+    let original_dimensions = image_buffer.dimensions();
+
+    let resized_buffers = Targets::new(args, &image_buffer)?.resize(image_buffer, options);
+    let dpi = resized_buffers.dpi.unwrap_or_else(|| args.get_dpi());
+
+    let output_path = get_output_path(path, out);
+    let mut saved_paths = Vec::new();
+
+    for (descriptor, variant_buffer) in &resized_buffers.variants {
+        let variant_path = get_variant_path(&output_path, descriptor);
+
+        save(variant_buffer, &variant_path, dpi)
+            .with_context(|| format!("{} can't be saved", variant_path.display()))?;
+        saved_paths.push(variant_path);
+    }
+
+    let output_path = match &resized_buffers.descriptor {
+        Some(descriptor) => get_variant_path(&output_path, descriptor),
+        None => output_path,
+    };
+
+    save(&resized_buffers.image_buffer, &output_path, dpi)
+        .with_context(|| format!("{} can't be saved", output_path.display()))?;
+
+    let resized_size = fs::metadata(&output_path)?.len();
+    saved_paths.push(output_path);
+
+    Ok(FileSummary {
+        original_dimensions,
+        original_size,
+        resized_dimensions: resized_buffers.image_buffer.dimensions(),
+        resized_size,
+        saved_paths,
+    })
+}
+
+/// Resizes all the files in parallel, with a progress bar, and prints the
+/// summary of the sizes and of the failures.
+///
+/// # Errors
+///
+/// Fails if the output directory can't be created, or if any file failed.
+///
+/// # Panics
+///
+/// Panics if a worker panicked.
+pub fn run(batch_args: &BatchArgs) -> Result<()> {
+    let start_time = Instant::now();
+    let BatchArgs { args, paths, out } = batch_args;
+
+    if let Some(out) = out {
+        fs::create_dir_all(out).with_context(|| format!("{} can't be created", out.display()))?;
+    }
+
+    let (files, mut failures) = expand_paths(paths, out.as_deref());
+    let options = ResizeOptions::from(args);
+    let progress_bar = display_progress_bar(files.len() as u64);
+
+    // The workers pick the next file until none is left.
+    let next_index = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::with_capacity(files.len()));
+    let workers = thread::available_parallelism()
+        .map_or(1, usize::from)
+        .min(files.len().max(1));
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let index = next_index.fetch_add(1, Ordering::SeqCst);
+                let Some(path) = files.get(index) else {
+                    break;
+                };
+
+                progress_bar.set_message(path.display().to_string());
+
+                let result = process_file(args, &options, path, out.as_deref());
+
+                results.lock().unwrap().push((index, result));
+                progress_bar.inc(1);
+            });
+        }
+    });
+
+    progress_bar.finish_and_clear();
+
+    // Keep the order of the files in the summary.
+    let mut results = results.into_inner().unwrap();
+    results.sort_by_key(|(index, _)| *index);
+
+    let print_dimensions =
+        |(width, height): (u32, u32)| style(format!("{width}x{height}px")).magenta();
+    let mut resized_count = 0;
+    let mut skipped_count = 0;
+
+    for (index, result) in results {
+        let path = &files[index];
+
+        match result {
+            Ok(summary) => {
+                resized_count += 1;
+
+                println!(
+                    "{}{}: {}, {} -> {}, {}.",
+                    STATS,
+                    path.display(),
+                    print_dimensions(summary.original_dimensions),
+                    style(BinaryBytes(summary.original_size)).magenta(),
+                    print_dimensions(summary.resized_dimensions),
+                    style(BinaryBytes(summary.resized_size)).magenta(),
+                );
+                for saved_path in &summary.saved_paths {
+                    println!("{}Saved to {}.", FLOPPY, style(saved_path.display()).dim());
+                }
+            }
+            // Skipped files, e.g. not matching the criteria, aren't failures.
+            Err(error) => match error.downcast::<Skip>() {
+                Ok(skip) => {
+                    skipped_count += 1;
+
+                    println!("{}", style(format!("{}: {skip}", path.display())).yellow());
+                }
+                Err(error) => failures.push((path.display().to_string(), format!("{error:#}"))),
+            },
+        }
+    }
+
+    for (path, reason) in &failures {
+        eprintln!("{}{}", BOOM, style(format!("{path}: {reason}")).red());
+    }
+
+    println!(
+        "{}{} of {} file(s) resized in {}.",
+        ZAP,
+        style(resized_count).magenta(),
+        style(resized_count + skipped_count + failures.len()).magenta(),
+        style(HumanDuration(start_time.elapsed())).magenta()
+    );
+
+    if !failures.is_empty() {
+        bail!("{} file(s) failed.", failures.len());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        path::{Path, PathBuf},
+    };
+
+    use super::{expand_paths, get_output_path, BatchArgs};
+
+    fn get_batch_args(words: &str) -> Result<BatchArgs, clap::Error> {
+        BatchArgs::try_parse_from(
+            &words
+                .split_whitespace()
+                .map(String::from)
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    #[test]
+    fn check_batch_args() {
+        let batch_args = get_batch_args("a.png shots/*.jpg --width 800 --out resized").unwrap();
+        assert_eq!(batch_args.paths, vec!["a.png", "shots/*.jpg"]);
+        assert_eq!(batch_args.out, Some(PathBuf::from("resized")));
+        assert_eq!(batch_args.args.width, Some(800));
+        assert!(batch_args.args.no_input);
+
+        // The flags can come first.
+        let batch_args = get_batch_args("--srcset-widths 320,640 a.png").unwrap();
+        assert_eq!(batch_args.paths, vec!["a.png"]);
+        assert_eq!(batch_args.out, None);
+
+        assert!(get_batch_args("--width 800").is_err());
+        assert!(get_batch_args("a.png --width 800 --emit html").is_err());
+        assert!(get_batch_args("a.png --width 800 --height 600 --ratio 2").is_err());
+    }
+
+    #[test]
+    fn check_expand_paths() {
+        let directory = std::env::temp_dir().join(format!("picst-batch-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        for name in ["a.png", "b.jpg", "notes.txt"] {
+            fs::write(directory.join(name), b"").unwrap();
+        }
+
+        let directory_path = directory.display().to_string();
+        let (files, failures) = expand_paths(
+            &[
+                directory_path.clone(),
+                format!("{directory_path}/*.png"),
+                format!("{directory_path}/*.gif"),
+                String::from("missing.png"),
+            ],
+            None,
+        );
+
+        // Only the images are kept from the directories and globs, once.
+        assert_eq!(
+            files,
+            vec![
+                directory.join("a.png"),
+                directory.join("b.jpg"),
+                PathBuf::from("missing.png"),
+            ]
+        );
+        assert_eq!(
+            failures,
+            vec![(
+                format!("{directory_path}/*.gif"),
+                String::from("no image files found")
+            )]
+        );
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn check_expand_paths_collisions() {
+        let directory =
+            std::env::temp_dir().join(format!("picst-batch-collisions-{}", std::process::id()));
+        for name in ["a/shot.png", "b/shot.png", "b/shot.webp", "b/other.png"] {
+            let path = directory.join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, b"").unwrap();
+        }
+
+        let directory_path = directory.display().to_string();
+        let paths = [format!("{directory_path}/a"), format!("{directory_path}/b")];
+
+        // The later files saved to the same path are rejected, next to the
+        // originals or in the output directory.
+        let (files, failures) = expand_paths(&paths, None);
+        assert_eq!(files.len(), 3);
+        assert_eq!(
+            failures,
+            vec![(
+                directory.join("b/shot.webp").display().to_string(),
+                format!(
+                    "would be saved to {}, like {}",
+                    directory.join("b/shot-picst.png").display(),
+                    directory.join("b/shot.png").display()
+                )
+            )]
+        );
+
+        let out = directory.join("out");
+        let (files, failures) = expand_paths(&paths, Some(&out));
+        assert_eq!(
+            files,
+            vec![directory.join("a/shot.png"), directory.join("b/other.png")]
+        );
+        assert_eq!(
+            failures
+                .iter()
+                .map(|(path, _)| path.as_str())
+                .collect::<Vec<_>>(),
+            vec![
+                directory.join("b/shot.png").to_str().unwrap(),
+                directory.join("b/shot.webp").to_str().unwrap(),
+            ]
+        );
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn check_get_output_path() {
+        assert_eq!(
+            get_output_path(Path::new("/tmp/shot.jpg"), None),
+            PathBuf::from("/tmp/shot-picst.png")
+        );
+        assert_eq!(
+            get_output_path(Path::new("shots/shot.JPG"), Some(Path::new("out"))),
+            PathBuf::from("out/shot.JPG")
+        );
+        assert_eq!(
+            get_output_path(Path::new("shots/shot.webp"), Some(Path::new("out"))),
+            PathBuf::from("out/shot.png")
+        );
+    }
+}
@@ -0,0 +1,160 @@
+use anyhow::{anyhow, Result};
+use clap::ValueEnum;
+use image::{imageops, Rgb, Rgba, RgbaImage};
+
+/// Layout of the collected images once stitched.
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub enum Layout {
+    /// Side by side, from left to right.
+    #[default]
+    Horizontal,
+    /// Stacked, from top to bottom.
+    Vertical,
+    /// Rows of the same number of images, as close to a square as possible.
+    Grid,
+}
+
+impl Layout {
+    /// Returns the number of columns and rows for the given number of images,
+    /// as a tuple of (columns, rows).
+    fn get_grid_size(self, count: usize) -> (usize, usize) {
+        let count = count.max(1);
+
+        match self {
+            Layout::Horizontal => (count, 1),
+            Layout::Vertical => (1, count),
+            Layout::Grid => {
+                let columns = (count as f64).sqrt().ceil() as usize;
+
+                (columns, (count + columns - 1) / columns)
+            }
+        }
+    }
+}
+
+/// Stitches the images into a single one following the layout, with the
+/// spacing in pixels between them, on the background color.
+/// Each column is as wide as its widest image, each row as tall as its
+/// tallest image, the images being centered in their cell.
+/// Note: the background is transparent if no color is given.
+///
+/// # Errors
+///
+/// Fails if the stitched image would be too large, e.g. with a huge spacing.
+pub fn stitch(
+    images: &[RgbaImage],
+    layout: Layout,
+    spacing: u32,
+    background: Option<Rgb<u8>>,
+) -> Result<RgbaImage> {
+    let (columns, rows) = layout.get_grid_size(images.len());
+    let mut widths = vec![0u32; columns];
+    let mut heights = vec![0u32; rows];
+
+    for (index, image) in images.iter().enumerate() {
+        let (column, row) = (index % columns, index / columns);
+
+        widths[column] = widths[column].max(image.width());
+        heights[row] = heights[row].max(image.height());
+    }
+
+    // Returns the offset of each cell, along with the total length.
+    let get_offsets = |lengths: &[u32]| {
+        let mut offsets = Vec::with_capacity(lengths.len());
+        let mut total = 0u32;
+
+        for length in lengths {
+            if !offsets.is_empty() {
+                total = total.checked_add(spacing)?;
+            }
+
+            offsets.push(total);
+            total = total.checked_add(*length)?;
+        }
+
+        Some((offsets, total.max(1)))
+    };
+    let too_large = || anyhow!("The stitched image would be too large.");
+    let (x_offsets, width) = get_offsets(&widths).ok_or_else(too_large)?;
+    let (y_offsets, height) = get_offsets(&heights).ok_or_else(too_large)?;
+
+    // The buffer length must fit in memory addresses too.
+    usize::try_from(u64::from(width) * u64::from(height))
+        .ok()
+        .and_then(|area| area.checked_mul(4))
+        .ok_or_else(too_large)?;
+
+    let background = background.map_or(Rgba([0, 0, 0, 0]), |Rgb([red, green, blue])| {
+        Rgba([red, green, blue, 255])
+    });
+    let mut stitched = RgbaImage::from_pixel(width, height, background);
+
+    for (index, image) in images.iter().enumerate() {
+        let (column, row) = (index % columns, index / columns);
+        let x = x_offsets[column] + (widths[column] - image.width()) / 2;
+        let y = y_offsets[row] + (heights[row] - image.height()) / 2;
+
+        imageops::overlay(&mut stitched, image, i64::from(x), i64::from(y));
+    }
+
+    Ok(stitched)
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgb, Rgba, RgbaImage};
+
+    use super::{stitch, Layout};
+
+    /// Returns three images of different sizes and colors.
+    fn get_images() -> Vec<RgbaImage> {
+        vec![
+            RgbaImage::from_pixel(4, 2, Rgba([255, 0, 0, 255])),
+            RgbaImage::from_pixel(2, 4, Rgba([0, 255, 0, 255])),
+            RgbaImage::from_pixel(2, 2, Rgba([0, 0, 255, 255])),
+        ]
+    }
+
+    #[test]
+    fn check_get_grid_size() {
+        assert_eq!(Layout::Horizontal.get_grid_size(3), (3, 1));
+        assert_eq!(Layout::Vertical.get_grid_size(3), (1, 3));
+        assert_eq!(Layout::Grid.get_grid_size(1), (1, 1));
+        assert_eq!(Layout::Grid.get_grid_size(3), (2, 2));
+        assert_eq!(Layout::Grid.get_grid_size(5), (3, 2));
+        assert_eq!(Layout::Grid.get_grid_size(9), (3, 3));
+    }
+
+    #[test]
+    fn check_stitch() {
+        let white = Some(Rgb([255, 255, 255]));
+
+        // The images are centered in their cell, with the spacing between
+        // them.
+        let stitched = stitch(&get_images(), Layout::Horizontal, 1, white).unwrap();
+        assert_eq!(stitched.dimensions(), (4 + 1 + 2 + 1 + 2, 4));
+        assert_eq!(*stitched.get_pixel(0, 0), Rgba([255, 255, 255, 255]));
+        assert_eq!(*stitched.get_pixel(0, 1), Rgba([255, 0, 0, 255]));
+        assert_eq!(*stitched.get_pixel(4, 0), Rgba([255, 255, 255, 255]));
+        assert_eq!(*stitched.get_pixel(5, 0), Rgba([0, 255, 0, 255]));
+        assert_eq!(*stitched.get_pixel(8, 1), Rgba([0, 0, 255, 255]));
+
+        let stitched = stitch(&get_images(), Layout::Vertical, 0, None).unwrap();
+        assert_eq!(stitched.dimensions(), (4, 2 + 4 + 2));
+        assert_eq!(*stitched.get_pixel(0, 2), Rgba([0, 0, 0, 0]));
+        assert_eq!(*stitched.get_pixel(1, 2), Rgba([0, 255, 0, 255]));
+
+        // The third image starts the second row.
+        let stitched = stitch(&get_images(), Layout::Grid, 0, None).unwrap();
+        assert_eq!(stitched.dimensions(), (4 + 2, 4 + 2));
+        assert_eq!(*stitched.get_pixel(1, 4), Rgba([0, 0, 255, 255]));
+
+        assert_eq!(
+            stitch(&[], Layout::Grid, 0, None).unwrap().dimensions(),
+            (1, 1)
+        );
+
+        // The spacing can't overflow the dimensions.
+        assert!(stitch(&get_images(), Layout::Horizontal, u32::MAX, None).is_err());
+    }
+}
@@ -0,0 +1,67 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Global allocator keeping track of the bytes allocated per thread, so that
+/// the tests running in parallel don't interfere, and of the allocations of a
+/// given size across all the threads.
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATED_BYTES: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Size of the allocations counted across all the threads, if not zero.
+static TRACKED_SIZE: AtomicUsize = AtomicUsize::new(0);
+
+/// Number of allocations of the tracked size.
+static TRACKED_ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+fn record(size: usize) {
+    // The thread local might already be destroyed.
+    let _ = ALLOCATED_BYTES.try_with(|allocated| allocated.set(allocated.get() + size));
+
+    if size != 0 && size == TRACKED_SIZE.load(Ordering::SeqCst) {
+        TRACKED_ALLOCATIONS.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        record(layout.size());
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        record(new_size);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Returns the bytes allocated so far by the current thread.
+pub(crate) fn get_allocated_bytes() -> usize {
+    ALLOCATED_BYTES.with(Cell::get)
+}
+
+/// Starts counting the allocations of the given size, whatever the thread,
+/// e.g. the copies of an image buffer handed to the blocking pool.
+/// Note: the size should be unique to the test, the other tests running in
+/// parallel.
+pub(crate) fn track_allocations_of(size: usize) {
+    TRACKED_ALLOCATIONS.store(0, Ordering::SeqCst);
+    TRACKED_SIZE.store(size, Ordering::SeqCst);
+}
+
+/// Returns the number of allocations of the tracked size so far.
+pub(crate) fn get_tracked_allocations() -> usize {
+    TRACKED_ALLOCATIONS.load(Ordering::SeqCst)
+}
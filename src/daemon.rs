@@ -0,0 +1,426 @@
+use std::{
+    env,
+    fs::{self, DirBuilder},
+    io::ErrorKind,
+    iter::once,
+    os::unix::fs::{DirBuilderExt, MetadataExt},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::{anyhow, bail, Result};
+use arboard::Clipboard;
+use clap::{error::ErrorKind as ClapErrorKind, CommandFactory, Parser, Subcommand};
+use dialoguer::console::style;
+use nix::unistd::getuid;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{UnixListener, UnixStream},
+    time::timeout,
+};
+
+use crate::{
+    args::Args,
+    image_data::{as_image_data, get_hash},
+    state::SharedState,
+};
+
+/// Name of the control socket in the runtime directory.
+static SOCKET_NAME: &str = "picst.sock";
+
+/// Time given to the clients to send their request.
+static READ_TIMEOUT_MS: u64 = 1000;
+
+/// First line of the responses, followed by the message.
+static OK: &str = "ok";
+static ERROR: &str = "error";
+
+/// Commands understood by the daemon.
+#[derive(Clone, Debug, PartialEq, Subcommand)]
+pub enum Request {
+    /// Replaces the flags used for the next images, e.g. `set --width 800`.
+    /// The flags only read at startup, e.g. `--daemon` or `--tui`, are
+    /// rejected.
+    Set {
+        /// Flags applied to the next images, as on the command line.
+        #[arg(allow_hyphen_values = true, trailing_var_arg = true)]
+        flags: Vec<String>,
+    },
+    /// Stops processing the copied images.
+    Pause,
+    /// Resumes processing the copied images, ignoring the ones copied while
+    /// paused.
+    Resume,
+    /// Prints the state of the daemon.
+    Status,
+    /// Stitches the images collected so far, with `--collect`.
+    Stitch,
+    /// Puts the original of the last processed image back in the clipboard.
+    Undo,
+}
+
+/// Parser for the requests received on the socket.
+#[derive(Debug, Parser)]
+#[command(name = "picst ctl")]
+struct RequestParser {
+    #[command(subcommand)]
+    request: Request,
+}
+
+impl Request {
+    /// Returns the words sent on the socket.
+    fn to_words(&self) -> Vec<String> {
+        match self {
+            Request::Set { flags } => once(String::from("set")).chain(flags.clone()).collect(),
+            Request::Pause => vec![String::from("pause")],
+            Request::Resume => vec![String::from("resume")],
+            Request::Status => vec![String::from("status")],
+            Request::Stitch => vec![String::from("stitch")],
+            Request::Undo => vec![String::from("undo")],
+        }
+    }
+
+    /// Parses the words received on the socket.
+    fn from_words(words: &[&str]) -> Result<Self> {
+        Ok(RequestParser::try_parse_from(once("picst ctl").chain(words.iter().copied()))?.request)
+    }
+}
+
+/// Returns the socket path, in the runtime directory by default, or in a
+/// private directory of the temporary one if it's not set.
+///
+/// # Errors
+///
+/// Fails if the private directory can't be created or isn't private.
+pub fn get_socket_path(socket: Option<PathBuf>) -> Result<PathBuf> {
+    if let Some(socket) = socket {
+        return Ok(socket);
+    }
+
+    let directory = match env::var_os("XDG_RUNTIME_DIR") {
+        Some(directory) => PathBuf::from(directory),
+        None => get_private_dir(&env::temp_dir())?,
+    };
+
+    Ok(directory.join(SOCKET_NAME))
+}
+
+/// Returns the directory of the current user in the parent one, created with
+/// the `0700` permissions if missing.
+/// Note: the temporary directory is shared with the other users, who could
+/// otherwise create the socket first.
+fn get_private_dir(parent: &Path) -> Result<PathBuf> {
+    let uid = getuid();
+    let path = parent.join(format!("picst-{uid}"));
+
+    match DirBuilder::new().mode(0o700).create(&path) {
+        Err(error) if error.kind() != ErrorKind::AlreadyExists => return Err(error.into()),
+        _ => {}
+    }
+
+    // An existing directory must not be a link, nor be accessible to others.
+    let metadata = fs::symlink_metadata(&path)?;
+
+    if !metadata.is_dir() || metadata.uid() != uid.as_raw() || metadata.mode() & 0o077 != 0 {
+        bail!(
+            "{} is not a private directory, set `XDG_RUNTIME_DIR` or pass `--socket`.",
+            path.display()
+        );
+    }
+
+    Ok(path)
+}
+
+/// Binds the control socket, replacing the one left by a previous daemon.
+///
+/// # Errors
+///
+/// Fails if another daemon is listening or if the socket can't be bound.
+pub async fn bind(path: &Path) -> Result<UnixListener> {
+    if path.exists() {
+        if UnixStream::connect(path).await.is_ok() {
+            bail!("A daemon is already listening on {}", path.display());
+        }
+
+        std::fs::remove_file(path)?;
+    }
+
+    Ok(UnixListener::bind(path)?)
+}
+
+/// Parses the flags of a `set` request, the ones only read at startup being
+/// rejected rather than silently ignored.
+fn parse_set_flags(flags: &[String]) -> Result<Args, clap::Error> {
+    let args = Args::try_custom_parse_from(once("picst").chain(flags.iter().map(String::as_str)))?;
+
+    // The subcommands come first.
+    let startup_flags = [
+        (
+            args.command.is_some(),
+            flags.first().map_or("", String::as_str),
+        ),
+        (args.daemon, "--daemon"),
+        (args.socket.is_some(), "--socket"),
+        (args.is_tui(), "--tui"),
+        (args.watch_only, "--watch-only"),
+    ];
+    if let Some((_, flag)) = startup_flags.iter().find(|(is_set, _)| *is_set) {
+        return Err(Args::command().error(
+            ClapErrorKind::ArgumentConflict,
+            format!(
+                "The argument '{}' cannot be used with '{}'",
+                style(flag).yellow(),
+                style("picst ctl set").yellow()
+            ),
+        ));
+    }
+
+    Ok(args)
+}
+
+/// Applies the request to the state and returns a message for the client.
+pub(crate) fn handle(request: Request, state: &SharedState) -> Result<String> {
+    match request {
+        Request::Set { flags } => {
+            let args = parse_set_flags(&flags)
+                .map_err(|error| anyhow!(error.to_string().trim_end().to_owned()))?;
+
+            state.lock().unwrap().set_args(args, flags);
+
+            Ok(String::from("Flags updated for the next images."))
+        }
+        Request::Pause => {
+            state.lock().unwrap().set_paused(true);
+
+            Ok(String::from("Paused."))
+        }
+        Request::Resume => {
+            state.lock().unwrap().set_paused(false);
+
+            Ok(String::from("Resumed."))
+        }
+        Request::Status => Ok(state.lock().unwrap().get_status()),
+        Request::Stitch => match state.lock().unwrap().request_stitch() {
+            0 => bail!("Nothing to stitch."),
+            collected => Ok(format!("Stitching {collected} image(s).")),
+        },
+        Request::Undo => {
+            let original = {
+                let mut state = state.lock().unwrap();
+
+                if !state.keeps_originals() {
+                    bail!("Undo is not available.");
+                }
+
+                let original = state
+                    .take_original()
+                    .ok_or_else(|| anyhow!("Nothing to undo."))?;
+
+                // Flag the original as seen first, so that it's not resized
+                // again.
+                state.set_previous_hash(get_hash(&original));
+
+                original
+            };
+
+            // The state isn't locked while writing to the clipboard.
+            Clipboard::new()?.set_image(as_image_data(&original))?;
+
+            Ok(String::from("Original image moved back to the clipboard."))
+        }
+    }
+}
+
+/// Answers the requests sent on the control socket, one per connection.
+/// Note: each connection is answered on its own task, so that a slow client
+/// doesn't hold up the other ones.
+pub async fn serve(listener: UnixListener, state: SharedState) {
+    while let Ok((stream, _)) = listener.accept().await {
+        tokio::spawn(answer(stream, Arc::clone(&state)));
+    }
+}
+
+/// Answers the request sent on the connection, dropping it if the request
+/// isn't sent in time.
+async fn answer(mut stream: UnixStream, state: SharedState) {
+    let mut content = String::new();
+    let read = timeout(
+        Duration::from_millis(READ_TIMEOUT_MS),
+        stream.read_to_string(&mut content),
+    );
+
+    if !matches!(read.await, Ok(Ok(_))) {
+        return;
+    }
+
+    let words: Vec<&str> = content.lines().collect();
+    let response = match Request::from_words(&words).and_then(|request| handle(request, &state)) {
+        Ok(message) => format!("{OK}\n{message}"),
+        Err(error) => format!("{ERROR}\n{error}"),
+    };
+
+    // The client might be gone already.
+    let _ = stream.write_all(response.as_bytes()).await;
+}
+
+/// Sends the request to the daemon and returns its message.
+///
+/// # Errors
+///
+/// Fails if no daemon is listening or if the daemon reports an error.
+pub async fn send(path: &Path, request: &Request) -> Result<String> {
+    let mut stream = UnixStream::connect(path)
+        .await
+        .map_err(|error| anyhow!("No daemon listening on {}: {error}", path.display()))?;
+
+    stream
+        .write_all(request.to_words().join("\n").as_bytes())
+        .await?;
+    stream.shutdown().await?;
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await?;
+
+    match response.split_once('\n') {
+        Some((status, message)) if status == OK => Ok(message.to_owned()),
+        Some((_, message)) => bail!("{message}"),
+        None => bail!("Invalid response from the daemon."),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env,
+        os::unix::fs::PermissionsExt,
+        process,
+        sync::{Arc, Mutex},
+    };
+
+    use clap::Parser;
+    use tokio::net::UnixStream;
+
+    use super::{bind, get_private_dir, handle, send, serve, Request};
+    use crate::{args::Args, state::State};
+
+    #[test]
+    fn check_request_round_trip() {
+        let requests = [
+            Request::Set {
+                flags: vec![String::from("--width"), String::from("800")],
+            },
+            Request::Pause,
+            Request::Resume,
+            Request::Status,
+            Request::Stitch,
+            Request::Undo,
+        ];
+
+        for request in requests {
+            let words = request.to_words();
+            let words: Vec<&str> = words.iter().map(String::as_str).collect();
+
+            assert_eq!(Request::from_words(&words).unwrap(), request);
+        }
+
+        assert!(Request::from_words(&["restart"]).is_err());
+    }
+
+    #[test]
+    fn check_set_rejects_startup_flags() {
+        let state = Arc::new(Mutex::new(State::new(
+            Args::parse_from(["picst"]),
+            Vec::new(),
+            true,
+        )));
+        let set = |flags: &str| {
+            let flags = flags.split_whitespace().map(String::from).collect();
+
+            handle(Request::Set { flags }, &state)
+        };
+
+        assert!(set("--width 800 --watch-dir .").is_ok());
+        assert_eq!(state.lock().unwrap().get_args().width, Some(800));
+
+        // The flags only read at startup are rejected, the state being left
+        // untouched.
+        for flags in [
+            "--width 400 --daemon",
+            "--width 400 --daemon --socket picst.sock",
+            "--width 400 --watch-dir . --watch-only",
+            "batch shot.png --width 400",
+            "ctl status",
+        ] {
+            let error = set(flags).unwrap_err().to_string();
+
+            assert!(error.contains("cannot be used with"), "{flags}: {error}");
+        }
+        #[cfg(feature = "tui")]
+        assert!(set("--width 400 --tui").is_err());
+        assert_eq!(state.lock().unwrap().get_args().width, Some(800));
+    }
+
+    #[tokio::test]
+    async fn check_control_socket() {
+        let path = env::temp_dir().join(format!("picst-test-{}.sock", process::id()));
+        let state = Arc::new(Mutex::new(State::new(
+            Args::parse_from(["picst"]),
+            Vec::new(),
+            false,
+        )));
+
+        let listener = bind(&path).await.unwrap();
+        tokio::spawn(serve(listener, Arc::clone(&state)));
+
+        // A second daemon can't take over the socket.
+        assert!(bind(&path).await.is_err());
+
+        // A client not sending its request doesn't hold up the other ones.
+        let _idle_client = UnixStream::connect(&path).await.unwrap();
+
+        send(&path, &Request::Pause).await.unwrap();
+        assert!(state.lock().unwrap().is_paused());
+
+        let request = Request::Set {
+            flags: vec![String::from("--width-percent"), String::from("50")],
+        };
+        send(&path, &request).await.unwrap();
+        assert_eq!(state.lock().unwrap().get_args().width_percent, Some(50));
+
+        assert_eq!(
+            send(&path, &Request::Status).await.unwrap(),
+            "Paused, 0 image(s) processed, flags: --width-percent 50."
+        );
+
+        // Invalid flags are reported and the previous ones are kept.
+        let request = Request::Set {
+            flags: vec![String::from("--width-percent"), String::from("half")],
+        };
+        assert!(send(&path, &request).await.is_err());
+        assert_eq!(state.lock().unwrap().get_args().width_percent, Some(50));
+
+        assert!(send(&path, &Request::Undo).await.is_err());
+        assert!(send(&path, &Request::Stitch).await.is_err());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn check_private_dir() {
+        let parent = env::temp_dir().join(format!("picst-private-{}", process::id()));
+        std::fs::create_dir_all(&parent).unwrap();
+
+        let path = get_private_dir(&parent).unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+
+        // The existing directory is reused as long as it's private.
+        assert_eq!(get_private_dir(&parent).unwrap(), path);
+
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o777)).unwrap();
+        assert!(get_private_dir(&parent).is_err());
+
+        std::fs::remove_dir_all(parent).unwrap();
+    }
+}
@@ -0,0 +1,164 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+
+use anyhow::Result;
+use base64::{
+    alphabet,
+    engine::{
+        general_purpose::{GeneralPurpose, GeneralPurposeConfig},
+        DecodePaddingMode,
+    },
+    Engine,
+};
+use image::{guess_format, ImageFormat, RgbaImage};
+
+use crate::{encoder::encode_png, skip::Skip};
+
+/// Base64 engine accepting both padded and unpadded payloads.
+const ENGINE: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::STANDARD,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// Formats which can be decoded from the clipboard text.
+static FORMATS: &[ImageFormat; 4] = &[
+    ImageFormat::Png,
+    ImageFormat::Jpeg,
+    ImageFormat::Gif,
+    ImageFormat::WebP,
+];
+
+/// Number of base64 characters decoded to detect the format, i.e. 12 bytes
+/// which are enough for all the magic numbers.
+static MAGIC_LENGTH: usize = 16;
+
+/// Parses the text of the clipboard as an encoded image, either an image data
+/// URI, e.g. `data:image/png;base64,...`, or a raw base64 payload.
+/// Returns the base64 payload if it starts with the magic number of a
+/// supported format, the rest being only decoded once known to be new.
+pub(crate) fn parse_encoded_image(text: &str) -> Option<&str> {
+    let text = text.trim();
+
+    let payload = match text.strip_prefix("data:") {
+        Some(data_uri) => {
+            let (header, payload) = data_uri.split_once(',')?;
+
+            (header.starts_with("image/") && header.ends_with(";base64")).then_some(payload)?
+        }
+        None => text,
+    };
+
+    let magic = payload
+        .chars()
+        .filter(|character| !character.is_ascii_whitespace())
+        .take(MAGIC_LENGTH)
+        .collect::<String>();
+    let format = guess_format(&ENGINE.decode(magic).ok()?).ok()?;
+
+    FORMATS.contains(&format).then_some(payload)
+}
+
+/// Returns the hash of a base64 payload.
+pub(crate) fn get_payload_hash(payload: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+
+    payload.hash(&mut hasher);
+
+    hasher.finish()
+}
+
+/// Decodes a base64 payload as an image buffer.
+/// Note: payloads which can't be decoded are skipped.
+pub(crate) fn decode_image(payload: &str) -> Result<RgbaImage> {
+    // Encoded images are often wrapped over several lines.
+    let payload = payload
+        .chars()
+        .filter(|character| !character.is_ascii_whitespace())
+        .collect::<String>();
+
+    let bytes = ENGINE
+        .decode(payload)
+        .map_err(|error| Skip::new(format!("the base64 text can't be decoded, {error}.")))?;
+
+    image::load_from_memory(&bytes)
+        .map(|image| image.to_rgba8())
+        .map_err(|error| Skip::new(format!("the encoded image can't be decoded, {error}.")).into())
+}
+
+/// Encodes the image buffer as a PNG data URI, with density metadata.
+pub(crate) fn encode_data_uri(image_buffer: &RgbaImage, dpi: u16) -> Result<String> {
+    Ok(format!(
+        "data:image/png;base64,{}",
+        ENGINE.encode(encode_png(image_buffer, dpi)?)
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::Engine;
+    use image::{Rgba, RgbaImage};
+
+    use super::{decode_image, encode_data_uri, parse_encoded_image, ENGINE};
+    use crate::{encoder::encode_png, skip::Skip};
+
+    #[test]
+    fn check_parse_encoded_image() {
+        let image_buffer = RgbaImage::from_pixel(3, 2, Rgba([255, 0, 0, 255]));
+        let payload = ENGINE.encode(encode_png(&image_buffer, 96).unwrap());
+
+        // Data URIs.
+        let data_uri = format!("data:image/png;base64,{payload}");
+        assert_eq!(parse_encoded_image(&data_uri), Some(payload.as_str()));
+
+        // Raw payloads, e.g. copied from JSON.
+        let wrapped = format!(" {}\n{} ", &payload[..10], &payload[10..]);
+        assert_eq!(
+            parse_encoded_image(&wrapped),
+            Some(format!("{}\n{}", &payload[..10], &payload[10..]).as_str())
+        );
+        assert_eq!(
+            decode_image(parse_encoded_image(&wrapped).unwrap()).unwrap(),
+            image_buffer
+        );
+
+        // Anything else is not an encoded image.
+        assert_eq!(parse_encoded_image("data:text/plain;base64,SGVsbG8="), None);
+        assert_eq!(
+            parse_encoded_image(&format!("data:image/png,{payload}")),
+            None
+        );
+        assert_eq!(parse_encoded_image(&ENGINE.encode("Hello, world!")), None);
+        assert_eq!(parse_encoded_image("Hello, world!"), None);
+    }
+
+    #[test]
+    fn check_encode_data_uri() {
+        // Emitted data URIs can be decoded back.
+        let image_buffer = RgbaImage::from_pixel(3, 2, Rgba([255, 0, 0, 255]));
+        let data_uri = encode_data_uri(&image_buffer, 96).unwrap();
+
+        assert_eq!(
+            decode_image(parse_encoded_image(&data_uri).unwrap()).unwrap(),
+            image_buffer
+        );
+    }
+
+    #[test]
+    fn check_decode_image() {
+        // Truncated images are skipped.
+        let image_buffer = RgbaImage::from_pixel(3, 2, Rgba([255, 0, 0, 255]));
+        let bytes = encode_png(&image_buffer, 96).unwrap();
+        let truncated = ENGINE.encode(&bytes[..bytes.len() / 2]);
+
+        assert!(decode_image(&truncated)
+            .unwrap_err()
+            .downcast::<Skip>()
+            .is_ok());
+        assert!(decode_image("not base64!")
+            .unwrap_err()
+            .downcast::<Skip>()
+            .is_ok());
+    }
+}
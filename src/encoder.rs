@@ -0,0 +1,180 @@
+use std::{fs, path::Path};
+
+use anyhow::Result;
+use image::{
+    codecs::jpeg::{JpegEncoder, PixelDensity},
+    imageops, DynamicImage, GenericImageView, Rgba, RgbaImage,
+};
+use png::{chunk, BitDepth, ColorType, Encoder};
+
+use crate::physical::DEFAULT_DPI;
+
+/// Number of inches in a meter, used by the PNG `pHYs` chunk.
+static INCHES_PER_METER: f32 = 39.370_08;
+
+/// Quality used for the JPEG encoding.
+static JPEG_QUALITY: u8 = 90;
+
+/// Maximum number of pixels encoded to estimate the byte size of a PNG.
+static SAMPLE_PIXEL_COUNT: u64 = 256 * 256;
+
+/// Encodes the image buffer as PNG with a `pHYs` chunk matching the DPI.
+///
+/// # Errors
+///
+/// Fails if the encoding fails.
+pub fn encode_png(image_buffer: &RgbaImage, dpi: u16) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let mut encoder = Encoder::new(&mut bytes, image_buffer.width(), image_buffer.height());
+
+    encoder.set_color(ColorType::Rgba);
+    encoder.set_depth(BitDepth::Eight);
+
+    let mut writer = encoder.write_header()?;
+
+    // The density is stored in pixels per meter for both axes, followed by
+    // the unit specifier (1 meaning meter).
+    let pixels_per_meter = (f32::from(dpi) * INCHES_PER_METER).round() as u32;
+    let mut density = Vec::with_capacity(9);
+    density.extend_from_slice(&pixels_per_meter.to_be_bytes());
+    density.extend_from_slice(&pixels_per_meter.to_be_bytes());
+    density.push(1);
+
+    writer.write_chunk(chunk::pHYs, &density)?;
+    writer.write_image_data(image_buffer.as_raw())?;
+    writer.finish()?;
+
+    Ok(bytes)
+}
+
+/// Encodes the image buffer as JPEG with a JFIF density matching the DPI.
+/// Note: JPEG has no alpha channel, which is therefore dropped.
+///
+/// # Errors
+///
+/// Fails if the encoding fails.
+pub fn encode_jpeg(image_buffer: &RgbaImage, dpi: u16) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let mut encoder = JpegEncoder::new_with_quality(&mut bytes, JPEG_QUALITY);
+
+    encoder.set_pixel_density(PixelDensity::dpi(dpi));
+    encoder.encode_image(&DynamicImage::ImageRgba8(image_buffer.clone()).to_rgb8())?;
+
+    Ok(bytes)
+}
+
+/// Estimates the byte size of the image once resized to the dimensions, as a
+/// tuple of (height, width), and encoded as PNG.
+/// Note: large images are estimated from a smaller sample, the byte size being
+/// assumed proportional to the pixel count.
+///
+/// # Errors
+///
+/// Fails if the encoding fails.
+pub(crate) fn estimate_png_size(
+    image: &impl GenericImageView<Pixel = Rgba<u8>>,
+    (height, width): (u32, u32),
+) -> Result<u64> {
+    let pixel_count = u64::from(height) * u64::from(width);
+    let factor = (SAMPLE_PIXEL_COUNT as f64 / pixel_count as f64)
+        .sqrt()
+        .min(1.0);
+    let sample = imageops::thumbnail(
+        image,
+        ((f64::from(width) * factor) as u32).max(1),
+        ((f64::from(height) * factor) as u32).max(1),
+    );
+    let sample_pixel_count = u64::from(sample.height()) * u64::from(sample.width());
+    let sample_size = encode_png(&sample, DEFAULT_DPI)?.len() as u64;
+
+    Ok(sample_size * pixel_count / sample_pixel_count)
+}
+
+/// Saves the image buffer with density metadata, the format being inferred
+/// from the extension and defaulting to PNG.
+///
+/// # Errors
+///
+/// Fails if the encoding or the writing of the file fails.
+pub fn save(image_buffer: &RgbaImage, path: &Path, dpi: u16) -> Result<()> {
+    let is_jpeg = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map_or(false, |extension| {
+            extension.eq_ignore_ascii_case("jpg") || extension.eq_ignore_ascii_case("jpeg")
+        });
+
+    let bytes = if is_jpeg {
+        encode_jpeg(image_buffer, dpi)?
+    } else {
+        encode_png(image_buffer, dpi)?
+    };
+
+    fs::write(path, bytes)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{
+        codecs::{jpeg::JpegDecoder, png::PngDecoder},
+        ImageDecoder, Rgba, RgbaImage,
+    };
+
+    use super::{encode_jpeg, encode_png, estimate_png_size};
+
+    #[test]
+    fn check_encode_png_density() {
+        let image_buffer = RgbaImage::from_pixel(4, 2, Rgba([255, 0, 0, 255]));
+        let bytes = encode_png(&image_buffer, 300).unwrap();
+
+        // The `pHYs` chunk holds 11811 pixels per meter, i.e. 300 DPI.
+        let position = bytes
+            .windows(4)
+            .position(|window| window == b"pHYs")
+            .unwrap();
+        assert_eq!(&bytes[position + 4..position + 8], &11811u32.to_be_bytes());
+        assert_eq!(&bytes[position + 8..position + 12], &11811u32.to_be_bytes());
+        assert_eq!(bytes[position + 12], 1);
+
+        let decoder = PngDecoder::new(bytes.as_slice()).unwrap();
+        assert_eq!(decoder.dimensions(), (4, 2));
+    }
+
+    #[test]
+    fn check_encode_jpeg_density() {
+        let image_buffer = RgbaImage::from_pixel(4, 2, Rgba([255, 0, 0, 255]));
+        let bytes = encode_jpeg(&image_buffer, 150).unwrap();
+
+        // The JFIF header stores the unit (1 meaning inches) followed by the
+        // horizontal and vertical densities.
+        let position = bytes
+            .windows(5)
+            .position(|window| window == b"JFIF\0")
+            .unwrap();
+        assert_eq!(bytes[position + 7], 1);
+        assert_eq!(&bytes[position + 8..position + 10], &150u16.to_be_bytes());
+        assert_eq!(&bytes[position + 10..position + 12], &150u16.to_be_bytes());
+
+        let decoder = JpegDecoder::new(bytes.as_slice()).unwrap();
+        assert_eq!(decoder.dimensions(), (4, 2));
+    }
+
+    #[test]
+    fn check_estimate_png_size() {
+        let image_buffer = RgbaImage::from_fn(1024, 1024, |x, y| {
+            Rgba([(x / 4) as u8, (y / 4) as u8, 128, 255])
+        });
+
+        // Small targets are encoded as is.
+        let size = estimate_png_size(&image_buffer, (100, 200)).unwrap();
+        let resized = image::imageops::thumbnail(&image_buffer, 200, 100);
+        assert_eq!(size, encode_png(&resized, 96).unwrap().len() as u64);
+
+        // Large targets are estimated from a sample.
+        let size = estimate_png_size(&image_buffer, (1024, 1024)).unwrap();
+        let actual_size = encode_png(&image_buffer, 96).unwrap().len() as u64;
+        assert!(size > actual_size / 2 && size < actual_size * 2);
+    }
+}
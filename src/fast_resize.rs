@@ -0,0 +1,280 @@
+use std::{num::NonZeroU32, thread};
+
+use fast_image_resize::{
+    pixels::U8x4, CropBox, DynamicImageView, FilterType, Image, ImageView, ImageViewMut, MulDiv,
+    PixelType, ResizeAlg, Resizer,
+};
+use image::RgbaImage;
+
+/// Number of bytes of a pixel.
+const PIXEL_SIZE: usize = 4;
+
+/// Returns the number of bands the passes are split into, one per core.
+fn get_band_count() -> usize {
+    thread::available_parallelism().map_or(1, usize::from)
+}
+
+/// Returns the length of the bands splitting `length` into `count` bands.
+fn get_band_length(length: u32, count: usize) -> u32 {
+    let count = u32::try_from(count).unwrap_or(u32::MAX).clamp(1, length);
+
+    (length + count - 1) / count
+}
+
+/// Resizes the cropped part of the source to the destination, with a
+/// Lanczos3 filter like the `image` backend.
+fn resize_view(
+    source: &[u8],
+    (source_width, source_height): (NonZeroU32, NonZeroU32),
+    crop_box: CropBox,
+    destination: &mut [u8],
+    (width, height): (NonZeroU32, NonZeroU32),
+) -> Option<()> {
+    let mut source_view =
+        ImageView::<U8x4>::from_buffer(source_width, source_height, source).ok()?;
+    source_view.set_crop_box(crop_box).ok()?;
+    let destination_view = ImageViewMut::<U8x4>::from_buffer(width, height, destination).ok()?;
+
+    Resizer::new(ResizeAlg::Convolution(FilterType::Lanczos3))
+        .resize(&source_view.into(), &mut destination_view.into())
+        .ok()
+}
+
+/// Resizes the source horizontally, to `width` pixels.
+/// Rows are resampled on their own, so the destination is split into bands
+/// of rows resized on all the cores.
+fn resize_rows(
+    source: &[u8],
+    (source_width, source_height): (NonZeroU32, NonZeroU32),
+    width: NonZeroU32,
+    band_count: usize,
+) -> Option<Vec<u8>> {
+    let band_height = get_band_length(source_height.get(), band_count);
+    let mut destination = vec![0; width.get() as usize * source_height.get() as usize * PIXEL_SIZE];
+
+    thread::scope(|scope| {
+        let handles: Vec<_> = destination
+            .chunks_mut(band_height as usize * width.get() as usize * PIXEL_SIZE)
+            .zip((0..).step_by(band_height as usize))
+            .map(|(band, top)| {
+                scope.spawn(move || {
+                    let height =
+                        NonZeroU32::new(u32::try_from(band.len() / PIXEL_SIZE).ok()? / width)?;
+                    let crop_box = CropBox {
+                        left: 0,
+                        top,
+                        width: source_width,
+                        height,
+                    };
+
+                    resize_view(
+                        source,
+                        (source_width, source_height),
+                        crop_box,
+                        band,
+                        (width, height),
+                    )
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .try_for_each(|handle| handle.join().ok().flatten())
+    })?;
+
+    Some(destination)
+}
+
+/// Resizes the source vertically, to `height` pixels.
+/// Columns are resampled on their own, so the destination is split into
+/// bands of columns resized on all the cores, then copied side by side.
+fn resize_columns(
+    source: &[u8],
+    (source_width, source_height): (NonZeroU32, NonZeroU32),
+    height: NonZeroU32,
+    band_count: usize,
+) -> Option<Vec<u8>> {
+    let band_width = get_band_length(source_width.get(), band_count);
+    let bands = thread::scope(|scope| {
+        let handles: Vec<_> = (0..source_width.get())
+            .step_by(band_width as usize)
+            .map(|left| {
+                scope.spawn(move || {
+                    let width = NonZeroU32::new(band_width.min(source_width.get() - left))?;
+                    let crop_box = CropBox {
+                        left,
+                        top: 0,
+                        width,
+                        height: source_height,
+                    };
+                    let mut band =
+                        vec![0; width.get() as usize * height.get() as usize * PIXEL_SIZE];
+
+                    resize_view(
+                        source,
+                        (source_width, source_height),
+                        crop_box,
+                        &mut band,
+                        (width, height),
+                    )?;
+
+                    Some(band)
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|handle| handle.join().ok().flatten())
+            .collect::<Option<Vec<_>>>()
+    })?;
+
+    let row_size = source_width.get() as usize * PIXEL_SIZE;
+    let mut destination = vec![0; row_size * height.get() as usize];
+
+    for (band, left) in bands
+        .iter()
+        .zip((0..).step_by(band_width as usize * PIXEL_SIZE))
+    {
+        let band_row_size = band.len() / height.get() as usize;
+
+        for (row, band_row) in destination
+            .chunks_exact_mut(row_size)
+            .zip(band.chunks_exact(band_row_size))
+        {
+            row[left..left + band_row_size].copy_from_slice(band_row);
+        }
+    }
+
+    Some(destination)
+}
+
+/// Resizes the image buffer with SIMD instructions on all the cores, using a
+/// Lanczos3 filter like the `image` backend.
+/// The alpha channel is taken into account for transparent images, at 8-bit
+/// precision.
+/// Returns `None` if the buffer can't be handled.
+pub(crate) fn resize(
+    image_buffer: &RgbaImage,
+    width: u32,
+    height: u32,
+    use_alpha: bool,
+) -> Option<RgbaImage> {
+    resize_in_bands(image_buffer, (width, height), use_alpha, get_band_count())
+}
+
+/// Resizes the image buffer horizontally then vertically, like
+/// `fast_image_resize` does, each pass being split into `band_count` bands
+/// resampled in parallel, so the result doesn't depend on the number of
+/// cores.
+fn resize_in_bands(
+    image_buffer: &RgbaImage,
+    (width, height): (u32, u32),
+    use_alpha: bool,
+    band_count: usize,
+) -> Option<RgbaImage> {
+    let source_size = (
+        NonZeroU32::new(image_buffer.width())?,
+        NonZeroU32::new(image_buffer.height())?,
+    );
+    let (width, height) = (NonZeroU32::new(width)?, NonZeroU32::new(height)?);
+    let mul_div = MulDiv::default();
+
+    // The colors are premultiplied by the alpha channel around the resize
+    // operation.
+    let premultiplied = if use_alpha {
+        let source: DynamicImageView =
+            ImageView::<U8x4>::from_buffer(source_size.0, source_size.1, image_buffer.as_raw())
+                .ok()?
+                .into();
+        let mut premultiplied = Image::new(source_size.0, source_size.1, PixelType::U8x4);
+
+        mul_div
+            .multiply_alpha(&source, &mut premultiplied.view_mut())
+            .ok()?;
+
+        Some(premultiplied)
+    } else {
+        None
+    };
+    let source = premultiplied
+        .as_ref()
+        .map_or(image_buffer.as_raw().as_slice(), Image::buffer);
+
+    let resized_rows = resize_rows(source, source_size, width, band_count)?;
+    let resized = resize_columns(&resized_rows, (width, source_size.1), height, band_count)?;
+    let mut destination = Image::from_vec_u8(width, height, resized, PixelType::U8x4).ok()?;
+
+    if use_alpha {
+        mul_div
+            .divide_alpha_inplace(&mut destination.view_mut())
+            .ok()?;
+    }
+
+    RgbaImage::from_raw(width.get(), height.get(), destination.into_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU32;
+
+    use fast_image_resize::{
+        pixels::U8x4, FilterType, Image, ImageView, PixelType, ResizeAlg, Resizer,
+    };
+    use image::{imageops, Rgba, RgbaImage};
+
+    use super::{resize, resize_in_bands};
+
+    fn get_gradient(width: u32, height: u32) -> RgbaImage {
+        RgbaImage::from_fn(width, height, |x, y| {
+            Rgba([(x * 4) as u8, (y * 5) as u8, ((x + y) * 2) as u8, 255])
+        })
+    }
+
+    #[test]
+    fn check_resize_matches_image_backend() {
+        let image_buffer = get_gradient(64, 48);
+
+        let fast = resize(&image_buffer, 32, 24, false).unwrap();
+        let reference = imageops::resize(&image_buffer, 32, 24, imageops::FilterType::Lanczos3);
+
+        assert_eq!(fast.dimensions(), (32, 24));
+        assert!(fast.pixels().zip(reference.pixels()).all(|(a, b)| a
+            .0
+            .iter()
+            .zip(b.0)
+            .all(|(a, b)| a.abs_diff(b) <= 3)));
+    }
+
+    #[test]
+    fn check_resize_in_bands_matches_single_pass() {
+        let image_buffer = get_gradient(61, 47);
+
+        for (width, height) in [(23, 17), (61, 20), (30, 47), (97, 83)] {
+            let source = ImageView::<U8x4>::from_buffer(
+                NonZeroU32::new(61).unwrap(),
+                NonZeroU32::new(47).unwrap(),
+                image_buffer.as_raw(),
+            )
+            .unwrap();
+            let mut destination = Image::new(
+                NonZeroU32::new(width).unwrap(),
+                NonZeroU32::new(height).unwrap(),
+                PixelType::U8x4,
+            );
+            Resizer::new(ResizeAlg::Convolution(FilterType::Lanczos3))
+                .resize(&source.into(), &mut destination.view_mut())
+                .unwrap();
+
+            let destination = destination.into_vec();
+
+            for band_count in 1..=5 {
+                let banded =
+                    resize_in_bands(&image_buffer, (width, height), false, band_count).unwrap();
+
+                assert_eq!(banded.as_raw(), &destination);
+            }
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use image::{ImageFormat, RgbaImage};
+use percent_encoding::percent_decode_str;
+
+use crate::skip::Skip;
+
+/// Scheme of the URIs listed by the file managers.
+static FILE_SCHEME: &str = "file://";
+
+/// Operations prepended by some file managers to the list of copied files.
+static OPERATIONS: &[&str; 2] = &["copy", "cut"];
+
+/// Suffix appended to the name of the resized copies written next to the
+/// original files.
+static RESIZED_SUFFIX: &str = "-picst";
+
+/// Parses a line of a copied file list, either a `file://` URI or an absolute
+/// path.
+fn parse_line(line: &str) -> Option<PathBuf> {
+    if let Some(uri) = line.strip_prefix(FILE_SCHEME) {
+        // The host, usually empty or `localhost`, is followed by the path.
+        let path = &uri[uri.find('/')?..];
+
+        return percent_decode_str(path)
+            .decode_utf8()
+            .ok()
+            .map(|path| PathBuf::from(path.as_ref()));
+    }
+
+    let path = Path::new(line);
+
+    path.is_absolute().then(|| path.to_path_buf())
+}
+
+/// Parses the text of the clipboard as a list of copied files, i.e. a
+/// `text/uri-list` or a list of absolute paths, one per line.
+/// Returns `None` if any line is something else, or if none of the files is
+/// an image.
+pub(crate) fn parse_file_list(text: &str) -> Option<Vec<PathBuf>> {
+    let mut lines = text
+        .lines()
+        .map(str::trim)
+        // Comments are allowed in URI lists.
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .peekable();
+
+    // Skip the operation, e.g. GNOME's `copy` or `cut`.
+    lines.next_if(|line| OPERATIONS.contains(line));
+
+    let paths = lines.map(parse_line).collect::<Option<Vec<_>>>()?;
+
+    // Only the image files are kept, based on their extension.
+    let image_paths = paths
+        .into_iter()
+        .filter(|path| ImageFormat::from_path(path).is_ok())
+        .collect::<Vec<_>>();
+
+    (!image_paths.is_empty()).then_some(image_paths)
+}
+
+/// Returns the hash of a list of copied files.
+pub(crate) fn get_file_list_hash(paths: &[PathBuf]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+
+    paths.hash(&mut hasher);
+
+    hasher.finish()
+}
+
+/// Loads an image file as an image buffer.
+/// Note: files which can't be read or decoded are skipped.
+pub(crate) fn load_image(path: &Path) -> Result<RgbaImage> {
+    image::open(path)
+        .map(|image| image.to_rgba8())
+        .map_err(|error| Skip::new(format!("{} can't be loaded, {error}.", path.display())).into())
+}
+
+/// Returns the path of the resized copy written next to the original file,
+/// e.g. `shot-picst.png` for `shot.jpg`.
+/// Note: the copies are always PNG files.
+#[must_use]
+pub fn get_resized_path(original_path: &Path) -> PathBuf {
+    let stem = original_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy())
+        .unwrap_or_default();
+
+    original_path.with_file_name(format!("{stem}{RESIZED_SUFFIX}.png"))
+}
+
+/// Returns the path of a resized image saved in the directory, named after
+/// the timestamp in milliseconds, e.g. `picst-1697040000000.png`.
+#[must_use]
+pub fn get_timestamped_path(directory: &Path, timestamp: u128) -> PathBuf {
+    directory.join(format!("picst-{timestamp}.png"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use super::{get_resized_path, get_timestamped_path, parse_file_list};
+    use crate::variants::get_variant_path;
+
+    #[test]
+    fn check_parse_file_list() {
+        // URI lists, with percent encoded characters and comments.
+        assert_eq!(
+            parse_file_list(
+                "# Copied files\r\nfile:///home/me/My%20Shot.png\r\nfile://localhost/tmp/b.JPG\r\n"
+            ),
+            Some(vec![
+                PathBuf::from("/home/me/My Shot.png"),
+                PathBuf::from("/tmp/b.JPG")
+            ])
+        );
+
+        // GNOME's list, starting with the operation.
+        assert_eq!(
+            parse_file_list("copy\nfile:///tmp/a.webp"),
+            Some(vec![PathBuf::from("/tmp/a.webp")])
+        );
+
+        // Plain paths.
+        assert_eq!(
+            parse_file_list("/tmp/a.png\n/tmp/notes.txt"),
+            Some(vec![PathBuf::from("/tmp/a.png")])
+        );
+
+        // Anything else is not a file list.
+        assert_eq!(parse_file_list("Hello /tmp/a.png"), None);
+        assert_eq!(parse_file_list("/tmp/a.png\nsome text"), None);
+        assert_eq!(parse_file_list("https://example.com/a.png"), None);
+        assert_eq!(parse_file_list("file:///tmp/notes.txt"), None);
+        assert_eq!(parse_file_list(""), None);
+    }
+
+    #[test]
+    fn check_get_resized_path() {
+        assert_eq!(
+            get_resized_path(Path::new("/tmp/shot.jpg")),
+            PathBuf::from("/tmp/shot-picst.png")
+        );
+        assert_eq!(
+            get_resized_path(Path::new("/tmp/archive.tar.png")),
+            PathBuf::from("/tmp/archive.tar-picst.png")
+        );
+    }
+
+    #[test]
+    fn check_get_timestamped_path() {
+        let path = get_timestamped_path(Path::new("out"), 1_697_040_000_000);
+        assert_eq!(path, PathBuf::from("out/picst-1697040000000.png"));
+
+        // The variants keep the timestamp, as documented.
+        assert_eq!(
+            get_variant_path(&path, "640w"),
+            PathBuf::from("out/picst-1697040000000-640w.png")
+        );
+        assert_eq!(
+            get_variant_path(
+                &get_timestamped_path(Path::new(""), 1_697_040_000_000),
+                "2x"
+            ),
+            PathBuf::from("picst-1697040000000-2x.png")
+        );
+    }
+}
@@ -0,0 +1,103 @@
+use anyhow::Result;
+use image::RgbaImage;
+
+use crate::{args::Args, skip::Skip};
+
+/// Returns why the image doesn't match the criteria of the arguments, if it
+/// doesn't.
+fn get_mismatch(args: &Args, width: u32, height: u32, byte_size: usize) -> Option<String> {
+    if let Some(min_width) = args.min_width.filter(|min_width| width < *min_width) {
+        return Some(format!(
+            "its width of {width}px is below `--min-width {min_width}`."
+        ));
+    }
+
+    if let Some(min_height) = args.min_height.filter(|min_height| height < *min_height) {
+        return Some(format!(
+            "its height of {height}px is below `--min-height {min_height}`."
+        ));
+    }
+
+    if let Some(min_bytes) = args
+        .min_bytes
+        .filter(|min_bytes| byte_size < *min_bytes as usize)
+    {
+        return Some(format!(
+            "its size of {byte_size} bytes is below `--min-bytes {min_bytes}`."
+        ));
+    }
+
+    if args.only_landscape && width <= height {
+        return Some(format!(
+            "it's not a landscape image ({width}x{height}px) with `--only-landscape`."
+        ));
+    }
+
+    None
+}
+
+/// Checks the image against the match criteria of the arguments, e.g.
+/// `--min-width`, before any prompt.
+/// Note: the images not matching them are skipped, i.e. left untouched.
+pub(crate) fn apply_filters(args: &Args, image_buffer: RgbaImage) -> Result<RgbaImage> {
+    let (width, height) = image_buffer.dimensions();
+
+    match get_mismatch(args, width, height, image_buffer.as_raw().len()) {
+        Some(reason) => Err(Skip::new(reason).into()),
+        None => Ok(image_buffer),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use image::RgbaImage;
+
+    use super::{apply_filters, get_mismatch};
+    use crate::{args::Args, skip::Skip};
+
+    fn get_args(flags: &str) -> Args {
+        Args::parse_from(format!("picst {flags}").split_whitespace())
+    }
+
+    #[test]
+    fn check_get_mismatch() {
+        let args = get_args("--min-width 100 --min-height 50");
+        assert_eq!(get_mismatch(&args, 100, 50, 0), None);
+        assert_eq!(
+            get_mismatch(&args, 32, 50, 0),
+            Some(String::from(
+                "its width of 32px is below `--min-width 100`."
+            ))
+        );
+        assert_eq!(
+            get_mismatch(&args, 100, 32, 0),
+            Some(String::from(
+                "its height of 32px is below `--min-height 50`."
+            ))
+        );
+
+        let args = get_args("--min-bytes 4096");
+        assert_eq!(get_mismatch(&args, 32, 32, 4096), None);
+        assert!(get_mismatch(&args, 16, 16, 1024).is_some());
+
+        // Square images aren't landscape ones.
+        let args = get_args("--only-landscape");
+        assert_eq!(get_mismatch(&args, 1920, 1080, 0), None);
+        assert!(get_mismatch(&args, 1080, 1080, 0).is_some());
+        assert!(get_mismatch(&args, 1080, 1920, 0).is_some());
+
+        assert_eq!(get_mismatch(&get_args(""), 1, 1, 0), None);
+    }
+
+    #[test]
+    fn check_apply_filters() {
+        let args = get_args("--min-width 2");
+
+        assert!(apply_filters(&args, RgbaImage::new(2, 1)).is_ok());
+        assert!(apply_filters(&args, RgbaImage::new(1, 2))
+            .unwrap_err()
+            .downcast::<Skip>()
+            .is_ok());
+    }
+}
@@ -0,0 +1,247 @@
+use std::{convert::TryFrom, env};
+
+use anyhow::{anyhow, Result};
+use image::{ImageBuffer, Pixel, Primitive, Rgba};
+
+use crate::report::{report, Level};
+
+/// Scales used for the select prompt.
+static SCALES: &[&str; 3] = &["Auto", "2x", "3x"];
+
+/// Environment variable used to configure the scale factor when it can't be
+/// detected.
+pub(crate) static SCALE_FACTOR_CONFIG: &str = "PICST_HIDPI_SCALE";
+
+/// Environment variables exposing the scale factor on Linux desktops.
+static PLATFORM_SCALE_FACTORS: &[&str; 2] = &["GDK_SCALE", "QT_SCALE_FACTOR"];
+
+/// Enumeration for the high DPI scale.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HiDpiScale {
+    /// Detected from the platform or taken from the configuration.
+    Auto,
+    /// Fixed integer factor.
+    Factor(u32),
+}
+
+impl HiDpiScale {
+    /// Returns the static scales.
+    pub(crate) fn get_items() -> &'static [&'static str; 3] {
+        SCALES
+    }
+
+    /// Resolves the scale to an integer factor.
+    /// Note: a detected `1x` factor is reported, since the scaling done by
+    /// the compositors isn't exposed to the applications.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the factor can't be detected with the `Auto` variant.
+    pub fn get_factor(self) -> Result<u32> {
+        match self {
+            HiDpiScale::Auto => {
+                let (name, factor) =
+                    detect_scale_factor(|name| env::var(name).ok()).ok_or_else(|| {
+                        anyhow!(
+                            "The display scale factor can't be detected, set it with `{}`.",
+                            SCALE_FACTOR_CONFIG
+                        )
+                    })?;
+
+                if factor == 1 {
+                    report(
+                        Level::Warning,
+                        format!(
+                            "The display scale factor is 1x according to `{name}`, the image is left as is. Set `{SCALE_FACTOR_CONFIG}` if the display is scaled."
+                        ),
+                    );
+                }
+
+                Ok(factor)
+            }
+            HiDpiScale::Factor(factor) => Ok(factor),
+        }
+    }
+}
+
+impl TryFrom<usize> for HiDpiScale {
+    type Error = &'static str;
+
+    fn try_from(value: usize) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(HiDpiScale::Auto),
+            1 => Ok(HiDpiScale::Factor(2)),
+            2 => Ok(HiDpiScale::Factor(3)),
+            // Unreachable.
+            _ => Err("Index cannot be converted to scale."),
+        }
+    }
+}
+
+/// Parses an integer scale factor, fractional ones being ignored since they
+/// can't be downsampled exactly.
+fn parse_scale_factor(value: &str) -> Option<u32> {
+    value
+        .trim()
+        .parse::<f32>()
+        .ok()
+        .filter(|factor| factor.fract() == 0. && *factor >= 1.)
+        .map(|factor| factor as u32)
+}
+
+/// Detects the display scale factor where the platform exposes it, falling
+/// back to the configuration, along with the name of the variable it's read
+/// from.
+/// Note: only the Linux desktops setting `GDK_SCALE` or `QT_SCALE_FACTOR`
+/// expose it, not macOS, Windows or the fractional scaling of the Wayland
+/// compositors.
+fn detect_scale_factor(get_var: impl Fn(&str) -> Option<String>) -> Option<(&'static str, u32)> {
+    PLATFORM_SCALE_FACTORS
+        .iter()
+        .chain([SCALE_FACTOR_CONFIG].iter())
+        .find_map(|name| {
+            get_var(name)
+                .as_deref()
+                .and_then(parse_scale_factor)
+                .map(|factor| (*name, factor))
+        })
+}
+
+/// Channel types which can be averaged by the downsampling.
+pub(crate) trait Channel: Primitive + 'static {
+    /// Converts the channel to a float.
+    fn to_float(self) -> f32;
+
+    /// Converts a float back to the channel.
+    fn from_float(value: f32) -> Self;
+}
+
+impl Channel for u8 {
+    fn to_float(self) -> f32 {
+        f32::from(self)
+    }
+
+    fn from_float(value: f32) -> Self {
+        value.round().clamp(0., 255.) as u8
+    }
+}
+
+impl Channel for f32 {
+    fn to_float(self) -> f32 {
+        self
+    }
+
+    fn from_float(value: f32) -> Self {
+        value
+    }
+}
+
+/// Downsamples the image buffer by an integer factor, each pixel being the
+/// exact average of a block of factor × factor pixels.
+/// Note: the trailing rows and columns not filling a block are dropped.
+pub(crate) fn downsample<T: Channel>(
+    image_buffer: &ImageBuffer<Rgba<T>, Vec<T>>,
+    factor: u32,
+) -> ImageBuffer<Rgba<T>, Vec<T>>
+where
+    Rgba<T>: Pixel<Subpixel = T>,
+{
+    let factor = factor.max(1);
+    let width = (image_buffer.width() / factor).max(1);
+    let height = (image_buffer.height() / factor).max(1);
+
+    ImageBuffer::from_fn(width, height, |x, y| {
+        let mut sums = [0f32; 4];
+        let mut count = 0f32;
+
+        for block_y in y * factor..((y + 1) * factor).min(image_buffer.height()) {
+            for block_x in x * factor..((x + 1) * factor).min(image_buffer.width()) {
+                let Rgba(channels) = image_buffer.get_pixel(block_x, block_y);
+
+                for (sum, channel) in sums.iter_mut().zip(channels) {
+                    *sum += channel.to_float();
+                }
+
+                count += 1.;
+            }
+        }
+
+        Rgba(sums.map(|sum| T::from_float(sum / count)))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgba, RgbaImage};
+
+    use super::{detect_scale_factor, downsample, parse_scale_factor, HiDpiScale, SCALES};
+
+    #[test]
+    fn check_hidpi_scale() {
+        assert_eq!(HiDpiScale::get_items(), SCALES);
+
+        let zero_to_scale: HiDpiScale = 0usize.try_into().unwrap();
+        assert_eq!(zero_to_scale, HiDpiScale::Auto);
+
+        let one_to_scale: HiDpiScale = 1usize.try_into().unwrap();
+        assert_eq!(one_to_scale, HiDpiScale::Factor(2));
+
+        let two_to_scale: HiDpiScale = 2usize.try_into().unwrap();
+        assert_eq!(two_to_scale, HiDpiScale::Factor(3));
+
+        assert_eq!(HiDpiScale::Factor(3).get_factor().unwrap(), 3);
+    }
+
+    #[test]
+    fn check_parse_scale_factor() {
+        assert_eq!(parse_scale_factor("2"), Some(2));
+        assert_eq!(parse_scale_factor(" 3.0 "), Some(3));
+        assert_eq!(parse_scale_factor("1.5"), None);
+        assert_eq!(parse_scale_factor("0"), None);
+        assert_eq!(parse_scale_factor("nope"), None);
+    }
+
+    #[test]
+    fn check_detect_scale_factor() {
+        let get_var = |name: &str| match name {
+            "QT_SCALE_FACTOR" => Some(String::from("1.5")),
+            "PICST_HIDPI_SCALE" => Some(String::from("2")),
+            _ => None,
+        };
+
+        // Fractional factors are skipped in favor of the next variables.
+        assert_eq!(detect_scale_factor(get_var), Some(("PICST_HIDPI_SCALE", 2)));
+        assert_eq!(
+            detect_scale_factor(|name| (name == "GDK_SCALE").then(|| String::from("1"))),
+            Some(("GDK_SCALE", 1))
+        );
+        assert_eq!(detect_scale_factor(|_| None), None);
+    }
+
+    #[test]
+    fn check_downsample() {
+        // A 2x2 checkerboard of black and white averages to mid gray.
+        let image_buffer = RgbaImage::from_fn(5, 4, |x, y| {
+            if (x + y) % 2 == 0 {
+                Rgba([0, 0, 0, 255])
+            } else {
+                Rgba([255, 255, 255, 255])
+            }
+        });
+        let downsampled = downsample(&image_buffer, 2);
+
+        assert_eq!(downsampled.dimensions(), (2, 2));
+        assert!(downsampled
+            .pixels()
+            .all(|pixel| *pixel == Rgba([128, 128, 128, 255])));
+
+        // A factor of three on a uniform image is lossless.
+        let image_buffer = RgbaImage::from_pixel(9, 6, Rgba([10, 20, 30, 40]));
+        let downsampled = downsample(&image_buffer, 3);
+
+        assert_eq!(downsampled.dimensions(), (3, 2));
+        assert!(downsampled
+            .pixels()
+            .all(|pixel| *pixel == Rgba([10, 20, 30, 40])));
+    }
+}
@@ -0,0 +1,174 @@
+use std::{
+    borrow::Cow,
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+
+use arboard::ImageData;
+use image::RgbaImage;
+
+/// Number of pixels sampled to detect a change of image cheaply.
+const SAMPLE_PIXEL_COUNT: usize = 64 * 1024;
+
+/// Converts the clipboard image to an image buffer, taking ownership of its
+/// bytes.
+/// Note: borrowed bytes are copied, which never happens with the images
+/// coming from the clipboard.
+#[must_use]
+pub fn into_image_buffer(image: ImageData<'_>) -> Option<RgbaImage> {
+    RgbaImage::from_raw(
+        u32::try_from(image.width).ok()?,
+        u32::try_from(image.height).ok()?,
+        image.bytes.into_owned(),
+    )
+}
+
+/// Returns a clipboard image borrowing the bytes of the image buffer.
+#[must_use]
+pub fn as_image_data(image_buffer: &RgbaImage) -> ImageData<'_> {
+    ImageData {
+        bytes: Cow::Borrowed(image_buffer.as_raw()),
+        height: image_buffer.height() as usize,
+        width: image_buffer.width() as usize,
+    }
+}
+
+/// Returns a hash of the image buffer, used to detect new images without
+/// keeping a copy of the previous one.
+#[must_use]
+pub fn get_hash(image_buffer: &RgbaImage) -> u64 {
+    let mut hasher = DefaultHasher::new();
+
+    image_buffer.dimensions().hash(&mut hasher);
+    image_buffer.as_raw().hash(&mut hasher);
+
+    hasher.finish()
+}
+
+/// Returns a hash of the dimensions and of evenly spaced pixels of the image
+/// buffer, cheap enough to poll on the clipboard content.
+/// Note: changes between the sampled pixels go unnoticed.
+#[must_use]
+pub fn get_sampled_hash(image_buffer: &RgbaImage) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    let pixel_count = image_buffer.as_raw().len() / 4;
+    let step = (pixel_count / SAMPLE_PIXEL_COUNT).max(1);
+
+    image_buffer.dimensions().hash(&mut hasher);
+
+    for pixel in image_buffer.as_raw().chunks_exact(4).step_by(step) {
+        pixel.hash(&mut hasher);
+    }
+
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use arboard::ImageData;
+    use image::{Rgba, RgbaImage};
+
+    use super::{as_image_data, get_hash, get_sampled_hash, into_image_buffer};
+    use crate::counting::get_allocated_bytes;
+
+    /// Size of the image used by the tests, a 4K screenshot.
+    static WIDTH: usize = 3840;
+    static HEIGHT: usize = 2160;
+
+    fn get_clipboard_image() -> ImageData<'static> {
+        ImageData {
+            bytes: Cow::Owned(vec![127; WIDTH * HEIGHT * 4]),
+            height: HEIGHT,
+            width: WIDTH,
+        }
+    }
+
+    #[test]
+    fn check_clipboard_round_trip_does_not_copy() {
+        let image = get_clipboard_image();
+        let image_size = image.bytes.len();
+
+        let before = get_allocated_bytes();
+
+        // From the clipboard to the image buffer, detecting the change and
+        // back to the clipboard.
+        let image_buffer = into_image_buffer(image).unwrap();
+        let hash = get_hash(&image_buffer);
+        let image_data = as_image_data(&image_buffer);
+
+        let allocated = get_allocated_bytes() - before;
+
+        assert_eq!(image_data.bytes.len(), image_size);
+        assert_ne!(hash, 0);
+        assert!(
+            allocated < image_size,
+            "{allocated} bytes allocated for an image of {image_size} bytes"
+        );
+    }
+
+    #[test]
+    fn check_borrowed_clipboard_image_is_copied_once() {
+        let bytes = vec![127; WIDTH * HEIGHT * 4];
+        let image = ImageData {
+            bytes: Cow::Borrowed(&bytes),
+            height: HEIGHT,
+            width: WIDTH,
+        };
+
+        let before = get_allocated_bytes();
+        let image_buffer = into_image_buffer(image).unwrap();
+        let allocated = get_allocated_bytes() - before;
+
+        assert_eq!(image_buffer.as_raw(), &bytes);
+        assert!(allocated >= bytes.len() && allocated < bytes.len() * 2);
+    }
+
+    #[test]
+    fn check_get_hash() {
+        let image_buffer = RgbaImage::from_pixel(2, 2, Rgba([1, 2, 3, 4]));
+        let mut other_buffer = image_buffer.clone();
+
+        assert_eq!(get_hash(&image_buffer), get_hash(&other_buffer));
+
+        other_buffer.put_pixel(1, 1, Rgba([1, 2, 3, 5]));
+        assert_ne!(get_hash(&image_buffer), get_hash(&other_buffer));
+
+        // Same bytes with other dimensions.
+        let transposed = RgbaImage::from_raw(4, 1, image_buffer.as_raw().clone()).unwrap();
+        assert_ne!(get_hash(&image_buffer), get_hash(&transposed));
+    }
+
+    #[test]
+    fn check_get_sampled_hash() {
+        let image_buffer = RgbaImage::from_pixel(2, 2, Rgba([1, 2, 3, 4]));
+        let mut other_buffer = image_buffer.clone();
+
+        // Small images are entirely sampled.
+        other_buffer.put_pixel(1, 1, Rgba([1, 2, 3, 5]));
+        assert_ne!(
+            get_sampled_hash(&image_buffer),
+            get_sampled_hash(&other_buffer)
+        );
+
+        let transposed = RgbaImage::from_raw(4, 1, image_buffer.as_raw().clone()).unwrap();
+        assert_ne!(
+            get_sampled_hash(&image_buffer),
+            get_sampled_hash(&transposed)
+        );
+
+        // A 4K screenshot replaced by another one.
+        let screenshot = RgbaImage::from_pixel(3840, 2160, Rgba([127, 127, 127, 255]));
+        let other_screenshot = RgbaImage::from_pixel(3840, 2160, Rgba([0, 0, 0, 255]));
+
+        assert_eq!(
+            get_sampled_hash(&screenshot),
+            get_sampled_hash(&screenshot.clone())
+        );
+        assert_ne!(
+            get_sampled_hash(&screenshot),
+            get_sampled_hash(&other_screenshot)
+        );
+    }
+}
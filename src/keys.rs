@@ -0,0 +1,289 @@
+use std::{
+    io::stdin,
+    os::unix::io::{AsRawFd, RawFd},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex, PoisonError, TryLockError,
+    },
+    thread,
+    time::Duration,
+};
+
+use anyhow::{anyhow, bail, Result};
+use arboard::Clipboard;
+use dialoguer::console::style;
+use nix::{
+    poll::{poll, PollFd, PollFlags},
+    sys::termios::{tcgetattr, tcsetattr, LocalFlags, SetArg, Termios},
+    unistd::read,
+};
+
+use crate::{
+    assets::KEYBOARD,
+    daemon::{handle, Request},
+    image_data::as_image_data,
+    state::SharedState,
+    wizard::prompt_settings,
+};
+
+/// Number of prompts running or waiting for their turn, the keys being left
+/// to them meanwhile.
+static PROMPT_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// Lock held while prompting, so that the prompts of the key controls and of
+/// the wizard never share the terminal.
+static PROMPT_LOCK: Mutex<()> = Mutex::new(());
+
+/// Timeout of the polling on the terminal, after which the prompt count is
+/// checked again.
+static POLL_TIMEOUT_MS: u64 = 100;
+
+/// Keys bound to the controls, printed along with the status line.
+static KEYS_HELP: &str =
+    "p: pause/resume, u: undo, r: redo with other settings, s: settings, q: quit";
+
+/// Returns whether some prompts are running or waiting for their turn.
+fn is_prompting() -> bool {
+    PROMPT_COUNT.load(Ordering::SeqCst) > 0
+}
+
+/// Runs the prompts once the other ones are done, the keys being left to
+/// them.
+/// Note: the prompts can't be nested.
+pub(crate) fn while_prompting<T>(prompt: impl FnOnce() -> T) -> T {
+    PROMPT_COUNT.fetch_add(1, Ordering::SeqCst);
+
+    let value = {
+        // A panicking prompt leaves the terminal usable by the next ones.
+        let _guard = PROMPT_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+
+        prompt()
+    };
+
+    PROMPT_COUNT.fetch_sub(1, Ordering::SeqCst);
+
+    value
+}
+
+/// Reads the next key pressed in the terminal, waiting for it until the poll
+/// timeout.
+/// Returns `None` if there's no key or if it's left to the wizard.
+///
+/// # Errors
+///
+/// Fails if the terminal can't be read anymore.
+fn read_key(fd: RawFd) -> Result<Option<char>> {
+    let mut poll_fds = [PollFd::new(fd, PollFlags::POLLIN)];
+
+    if poll(&mut poll_fds, POLL_TIMEOUT_MS as i32)? == 0 {
+        return Ok(None);
+    }
+
+    // The prompt lock is held while reading, so that a prompt starting in the
+    // meantime waits for the key to be read instead of losing it, and the key
+    // is left to the wizard if it started prompting before.
+    let _guard = match PROMPT_LOCK.try_lock() {
+        Ok(guard) => guard,
+        Err(TryLockError::Poisoned(error)) => error.into_inner(),
+        Err(TryLockError::WouldBlock) => return Ok(None),
+    };
+
+    if is_prompting() {
+        return Ok(None);
+    }
+
+    let mut buffer = [0u8; 1];
+
+    match read(fd, &mut buffer)? {
+        1 => Ok(Some(char::from(buffer[0]))),
+        _ => bail!("The terminal has been closed."),
+    }
+}
+
+/// Listener of the single key controls, restoring the terminal once dropped.
+#[derive(Debug)]
+pub struct KeyListener {
+    fd: RawFd,
+    original: Termios,
+}
+
+impl KeyListener {
+    /// Listens for the keys pressed in the terminal on a dedicated thread,
+    /// calling the closure for each of them.
+    /// The terminal is switched to the non-canonical mode without echo, so
+    /// that the keys are read without waiting for a new line, the output being
+    /// left untouched.
+    /// Note: the keys aren't read while the wizard prompts.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the standard input is not a terminal or can't be configured.
+    pub fn listen(mut on_key: impl FnMut(char) + Send + 'static) -> Result<Self> {
+        let fd = stdin().as_raw_fd();
+
+        let original =
+            tcgetattr(fd).map_err(|_| anyhow!("The keys can only be read from a terminal."))?;
+        let mut termios = original.clone();
+
+        termios
+            .local_flags
+            .remove(LocalFlags::ICANON | LocalFlags::ECHO);
+        tcsetattr(fd, SetArg::TCSANOW, &termios)?;
+
+        thread::spawn(move || loop {
+            if is_prompting() {
+                thread::sleep(Duration::from_millis(POLL_TIMEOUT_MS));
+
+                continue;
+            }
+
+            match read_key(fd) {
+                Ok(Some(key)) => on_key(key),
+                Ok(None) => {}
+                Err(_) => break,
+            }
+        });
+
+        Ok(Self { fd, original })
+    }
+}
+
+impl Drop for KeyListener {
+    fn drop(&mut self) {
+        // Nothing more can be done if the terminal is gone.
+        let _ = tcsetattr(self.fd, SetArg::TCSANOW, &self.original);
+    }
+}
+
+/// Prints the status line, i.e. the current mode along with the keys.
+///
+/// # Panics
+///
+/// Panics if the state is poisoned.
+pub fn print_status(state: &SharedState) {
+    println!(
+        "{}{} {}",
+        KEYBOARD,
+        state.lock().unwrap().get_status(),
+        style(format!("[{KEYS_HELP}]")).dim()
+    );
+}
+
+/// Applies the control bound to the key, returning a message for the user or
+/// `None` if the key isn't bound.
+/// Note: quitting with `q` is left to the caller, which has to restore the
+/// terminal first.
+///
+/// # Panics
+///
+/// Panics if the state is poisoned.
+pub fn handle_key(key: char, state: &SharedState) -> Option<Result<String>> {
+    let request = match key {
+        'p' if state.lock().unwrap().is_paused() => Request::Resume,
+        'p' => Request::Pause,
+        'u' => Request::Undo,
+        'r' => return Some(redo(state)),
+        's' => {
+            return Some(
+                change_settings(state)
+                    .map(|()| String::from("Settings updated for the next images.")),
+            )
+        }
+        // The collected images are stitched on `Enter`.
+        '\n' if state.lock().unwrap().get_args().collect.is_some() => Request::Stitch,
+        _ => return None,
+    };
+
+    Some(handle(request, state))
+}
+
+/// Prompts for the size specification of the next images.
+fn change_settings(state: &SharedState) -> Result<()> {
+    let args = state.lock().unwrap().get_args().clone();
+    let size_spec = prompt_settings(&args)?;
+
+    state.lock().unwrap().set_size_spec(size_spec);
+
+    Ok(())
+}
+
+/// Prompts for the size specification of the next images, and moves the
+/// original of the last processed image back to the clipboard so that it's
+/// resized again with it.
+fn redo(state: &SharedState) -> Result<String> {
+    {
+        let state = state.lock().unwrap();
+
+        if !state.keeps_originals() {
+            bail!("Redo is not available.");
+        }
+
+        if !state.has_original() {
+            bail!("Nothing to redo.");
+        }
+    }
+
+    change_settings(state)?;
+
+    let original = state
+        .lock()
+        .unwrap()
+        .take_original()
+        .ok_or_else(|| anyhow!("Nothing to redo."))?;
+
+    // Unlike the undo, the original isn't flagged as seen.
+    Clipboard::new()?.set_image(as_image_data(&original))?;
+
+    Ok(String::from(
+        "Original image moved back to the clipboard, resizing it again.",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicBool, Ordering},
+        thread,
+        time::Duration,
+    };
+
+    use nix::unistd::{close, pipe, write};
+
+    use super::{is_prompting, read_key, while_prompting};
+
+    #[test]
+    fn check_while_prompting_serializes_prompts() {
+        static IS_IN_PROMPT: AtomicBool = AtomicBool::new(false);
+
+        let prompt = || {
+            assert!(is_prompting());
+            assert!(!IS_IN_PROMPT.swap(true, Ordering::SeqCst));
+            thread::sleep(Duration::from_millis(50));
+            IS_IN_PROMPT.store(false, Ordering::SeqCst);
+        };
+
+        thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| while_prompting(prompt));
+            }
+        });
+
+        assert!(!is_prompting());
+    }
+
+    #[test]
+    fn check_read_key_leaves_keys_to_prompts() {
+        let (read_fd, write_fd) = pipe().unwrap();
+        write(write_fd, b"s").unwrap();
+
+        // The key pressed while prompting is left to the prompt.
+        assert_eq!(while_prompting(|| read_key(read_fd).unwrap()), None);
+
+        // And read once done, the other tests possibly prompting meanwhile.
+        let key = (0..100).find_map(|_| read_key(read_fd).unwrap());
+        assert_eq!(key, Some('s'));
+
+        close(write_fd).unwrap();
+        close(read_fd).unwrap();
+    }
+}
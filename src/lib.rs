@@ -0,0 +1,79 @@
+#![deny(clippy::pedantic, clippy::clone_on_ref_ptr)]
+#![allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_precision_loss,
+    clippy::cast_sign_loss,
+    clippy::float_cmp
+)]
+#![deny(unsafe_code, nonstandard_style)]
+#![doc = include_str!("../README.md")]
+#![forbid(rust_2021_compatibility)]
+#![warn(missing_debug_implementations, missing_docs)]
+
+pub use crate::{
+    hidpi::HiDpiScale,
+    physical::{PhysicalLength, PhysicalUnit},
+};
+
+mod adjustment;
+mod alpha;
+/// Flags of the command line interface, and the size specification they
+/// describe.
+pub mod args;
+/// Banner and emojis printed by the command line interface.
+pub mod assets;
+/// Batch processing of image files, directories and globs.
+pub mod batch;
+/// Stitching of the collected images.
+pub mod collect;
+#[cfg(test)]
+#[allow(unsafe_code)]
+mod counting;
+/// Local control socket used to drive a running daemon.
+#[cfg(unix)]
+pub mod daemon;
+mod data_uri;
+mod dimension;
+/// Encoding of the resized images with density metadata.
+pub mod encoder;
+#[cfg(feature = "fast-resize")]
+mod fast_resize;
+/// Image files copied from a file manager.
+pub mod file_list;
+mod filter;
+mod hidpi;
+/// Conversions between the clipboard images and the image buffers.
+pub mod image_data;
+/// Single key controls read from the terminal.
+#[cfg(unix)]
+pub mod keys;
+mod linear;
+mod physical;
+/// Computation of the resized dimensions, without any prompt.
+pub mod planner;
+/// Preview of the resized images in the terminal.
+pub mod preview;
+/// Messages reported to the user, printed or shown in the dashboard.
+pub mod report;
+/// Resize operation applied to the image buffers.
+pub mod resize;
+/// Resized image produced by the processing stream.
+pub mod resized_image;
+/// Error reported when an image is skipped.
+pub mod skip;
+/// Text snippets embedding the resized images.
+pub mod snippet;
+mod spinner;
+/// State shared between the processing stream and the controls.
+pub mod state;
+/// Processing stream polling on the clipboard content.
+pub mod stream;
+/// Full-screen terminal dashboard.
+#[cfg(feature = "tui")]
+pub mod tui;
+mod unit;
+mod validation;
+/// Responsive variants of the resized images.
+pub mod variants;
+mod watch;
+mod wizard;
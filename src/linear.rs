@@ -0,0 +1,117 @@
+use image::{Rgba32FImage, RgbaImage};
+
+use crate::alpha::{decode_channel, encode_channel, premultiply, unpremultiply};
+
+/// Decodes an sRGB channel to linear light.
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.040_45 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Encodes a linear light channel to sRGB.
+fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.003_130_8 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1. / 2.4) - 0.055
+    }
+}
+
+/// Converts the image buffer to premultiplied linear light, at float
+/// precision.
+/// Note: the alpha channel is already linear and kept as is.
+pub(crate) fn to_linear(image_buffer: &RgbaImage) -> Rgba32FImage {
+    // All the possible channel values are decoded once.
+    let lookup: Vec<f32> = (0..=u8::MAX)
+        .map(|value| srgb_to_linear(decode_channel(value)))
+        .collect();
+
+    premultiply(image_buffer, |value| lookup[usize::from(value)])
+}
+
+/// Converts the premultiplied linear light image buffer back to sRGB.
+pub(crate) fn to_srgb(image_buffer: &Rgba32FImage) -> RgbaImage {
+    unpremultiply(image_buffer, |value| {
+        encode_channel(linear_to_srgb(value.clamp(0., 1.)))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{imageops, Rgba, RgbaImage};
+
+    use super::{linear_to_srgb, srgb_to_linear, to_linear, to_srgb};
+
+    /// Returns a test image made of one pixel wide stripes, white and black on
+    /// the left half, red and green on the right half.
+    fn get_stripes() -> RgbaImage {
+        RgbaImage::from_fn(32, 16, |x, _| match (x < 16, x % 2 == 0) {
+            (true, true) => Rgba([255, 255, 255, 255]),
+            (true, false) => Rgba([0, 0, 0, 255]),
+            (false, true) => Rgba([255, 0, 0, 255]),
+            (false, false) => Rgba([0, 255, 0, 255]),
+        })
+    }
+
+    #[test]
+    fn check_transfer_functions() {
+        for value in 0..=255u8 {
+            let value = f32::from(value) / 255.;
+
+            assert!((linear_to_srgb(srgb_to_linear(value)) - value).abs() < 1e-5);
+        }
+
+        // Half of the light is encoded far above the middle of the range.
+        assert_eq!((linear_to_srgb(0.5) * 255.).round() as u8, 188);
+    }
+
+    #[test]
+    fn check_round_trip_is_lossless() {
+        let image_buffer = RgbaImage::from_fn(16, 16, |x, y| {
+            Rgba([(x * 16) as u8, (y * 16) as u8, 255 - (x * 16) as u8, 255])
+        });
+
+        assert_eq!(to_srgb(&to_linear(&image_buffer)), image_buffer);
+    }
+
+    #[test]
+    fn check_linear_downscale_keeps_brightness() {
+        let stripes = get_stripes();
+
+        // Resampling the sRGB bytes averages black and white to a dark gray.
+        let srgb = imageops::resize(&stripes, 8, 4, imageops::FilterType::Triangle);
+        assert!(srgb.get_pixel(2, 2)[0].abs_diff(128) <= 4);
+
+        // Resampling linear light keeps the perceived brightness.
+        let linear = to_srgb(&imageops::resize(
+            &to_linear(&stripes),
+            8,
+            4,
+            imageops::FilterType::Triangle,
+        ));
+        assert!(linear.get_pixel(2, 2)[0].abs_diff(188) <= 4);
+    }
+
+    #[test]
+    fn check_linear_downscale_keeps_hue() {
+        let stripes = get_stripes();
+
+        // Red and green stripes turn into a dark olive with sRGB bytes.
+        let srgb = imageops::resize(&stripes, 8, 4, imageops::FilterType::Triangle);
+        let Rgba([red, green, blue, _]) = *srgb.get_pixel(6, 2);
+        assert!(red.abs_diff(128) <= 4 && green.abs_diff(128) <= 4 && blue == 0);
+
+        // And into a bright yellow with linear light.
+        let linear = to_srgb(&imageops::resize(
+            &to_linear(&stripes),
+            8,
+            4,
+            imageops::FilterType::Triangle,
+        ));
+        let Rgba([red, green, blue, _]) = *linear.get_pixel(6, 2);
+        assert!(red.abs_diff(188) <= 4 && green.abs_diff(188) <= 4 && blue == 0);
+    }
+}
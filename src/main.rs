@@ -1,48 +1,103 @@
 #![deny(clippy::pedantic, clippy::clone_on_ref_ptr)]
-#![allow(
-    clippy::cast_possible_truncation,
-    clippy::cast_precision_loss,
-    clippy::cast_sign_loss,
-    clippy::float_cmp
-)]
 #![deny(unsafe_code, nonstandard_style)]
-#![doc = include_str!("../README.md")]
 #![forbid(rust_2021_compatibility)]
 #![warn(missing_debug_implementations, missing_docs)]
 
-use std::{borrow::Cow, io::Read};
+//! Command line interface of `picst`, a thin consumer of the library.
+
+#[cfg(unix)]
+use std::process;
+use std::{
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 use anyhow::Result;
-use arboard::{Clipboard, ImageData};
+use arboard::Clipboard;
 use dialoguer::console::style;
 use futures::{pin_mut, StreamExt};
-
-use crate::{
-    args::Args,
+#[cfg(feature = "tui")]
+use picst::tui;
+use picst::{
+    args::{Args, Command},
     assets::{BOOM, PICST},
+    batch::{run, BatchArgs},
+    encoder::save,
+    file_list::{get_resized_path, get_timestamped_path},
+    image_data::as_image_data,
+    report::{report, Level},
+    resized_image::ResizedImage,
+    snippet::Emit,
+    state::{SharedState, State},
     stream::get_stream,
+    variants::save_variants,
 };
-
-mod args;
-mod assets;
-mod dimension;
-mod resized_image;
-mod spinner;
-mod stream;
-mod unit;
-mod validation;
-mod wizard;
+#[cfg(unix)]
+use picst::{
+    assets::ANTENNA,
+    daemon::{bind, get_socket_path, send, serve},
+    keys::{handle_key, print_status, KeyListener},
+};
+#[cfg(unix)]
+use tokio::{select, signal, sync::mpsc};
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    // Do the arguments parsing upfront to ensure to exit directly.
+    let args = Args::custom_parse();
+
+    // Resize the files of the batch and exit.
+    if let Some(Command::Batch { words }) = &args.command {
+        return run(&BatchArgs::parse_from(words));
+    }
+
+    // Send the control commands to the running daemon and exit.
+    #[cfg(unix)]
+    if let Some(Command::Ctl { socket, request }) = &args.command {
+        println!(
+            "{}",
+            send(&get_socket_path(socket.clone())?, request).await?
+        );
+
+        return Ok(());
+    }
+
     // Display the banner.
     println!("{}", style(PICST).magenta());
 
-    // Do the arguments parsing upfront to ensure to exit directly.
-    let args = Args::custom_parse();
+    // Share the arguments with the controls, only the daemon and the key
+    // controls can restore the original images.
+    let flags = std::env::args().skip(1).collect();
+    let state = Arc::new(Mutex::new(State::new(
+        args.clone(),
+        flags,
+        args.is_daemon() || args.has_key_controls(),
+    )));
+
+    // Listen for the control commands.
+    #[cfg(unix)]
+    if args.daemon {
+        let path = get_socket_path(args.socket.clone())?;
+        let listener = bind(&path).await?;
+
+        tokio::spawn(serve(listener, Arc::clone(&state)));
+
+        println!(
+            "{}Listening for control commands on {}.",
+            ANTENNA,
+            style(path.display()).magenta()
+        );
+    }
+
+    // Listen for the single key controls.
+    #[cfg(unix)]
+    if args.has_key_controls() {
+        listen_keys(&state)?;
+    }
 
     // Get the stream.
-    let stream = get_stream(args);
+    let stream = get_stream(Arc::clone(&state));
 
     // Pin it on the stack.
     pin_mut!(stream);
@@ -50,35 +105,164 @@ async fn main() -> Result<()> {
     // Get an instance of the clipboard to consume the stream.
     let mut clipboard = Clipboard::new().unwrap();
 
+    // Show the dashboard instead of printing the statistics.
+    #[cfg(feature = "tui")]
+    if args.tui {
+        return tui::run(Arc::clone(&state), stream, |resized_image| {
+            let is_moved = move_to_clipboard(&mut clipboard, &state, resized_image)?;
+
+            // The saved files aren't picked from the watched directory.
+            state
+                .lock()
+                .unwrap()
+                .add_saved_paths(resized_image.get_saved_paths());
+
+            Ok(is_moved)
+        })
+        .await;
+    }
+
     while let Some(maybe_resized_image) = stream.next().await {
         let mut resized_image = maybe_resized_image?;
-        let image_buffer = resized_image.get_buffer();
-        let maybe_bytes: Result<Vec<u8>, _> = image_buffer.bytes().collect();
 
-        if let Ok(bytes) = maybe_bytes {
-            // Prepare a new image for the clipboard.
-            let image = ImageData {
-                bytes: Cow::from(bytes),
-                height: image_buffer.height() as usize,
-                width: image_buffer.width() as usize,
-            };
+        let is_moved = move_to_clipboard(&mut clipboard, &state, &mut resized_image)?;
 
-            if clipboard.set_image(image).is_ok() {
-                resized_image.get_stats();
+        // The saved files aren't picked from the watched directory.
+        state
+            .lock()
+            .unwrap()
+            .add_saved_paths(resized_image.get_saved_paths());
 
-                continue;
-            }
-
-            eprintln!(
-                "{}{}",
-                style("Moving the image to the clipboard failed!").red(),
-                BOOM
-            );
+        if is_moved {
+            resized_image.get_stats();
 
-            // New line for readability.
-            println!();
+            continue;
         }
+
+        eprintln!(
+            "{}{}",
+            style("Moving the image to the clipboard failed!").red(),
+            BOOM
+        );
+
+        // New line for readability.
+        println!();
     }
 
     Ok(())
 }
+
+/// Listens for the single key controls, printing their messages along with
+/// the status line.
+/// The terminal is restored before quitting, either with `q` or `Ctrl+C`.
+#[cfg(unix)]
+fn listen_keys(state: &SharedState) -> Result<()> {
+    let (quit_sender, mut quit_receiver) = mpsc::unbounded_channel();
+    let key_state = Arc::clone(state);
+    let key_listener = KeyListener::listen(move |key| {
+        if key == 'q' {
+            // The receiver is only gone once quitting.
+            let _ = quit_sender.send(());
+
+            return;
+        }
+
+        match handle_key(key, &key_state) {
+            Some(Ok(message)) => println!("{message}"),
+            Some(Err(error)) => eprintln!("{}{}", style(error).red(), BOOM),
+            None => return,
+        }
+
+        print_status(&key_state);
+    })?;
+
+    print_status(state);
+
+    tokio::spawn(async move {
+        let code = select! {
+            Some(()) = quit_receiver.recv() => 0,
+            Ok(()) = signal::ctrl_c() => 130,
+            else => 0,
+        };
+
+        drop(key_listener);
+        process::exit(code);
+    });
+
+    Ok(())
+}
+
+/// Saves the resized image if requested, and moves it to the clipboard either
+/// as image data or as a text snippet.
+/// Returns whether the clipboard has been written.
+fn move_to_clipboard(
+    clipboard: &mut Clipboard,
+    state: &SharedState,
+    resized_image: &mut ResizedImage,
+) -> Result<bool> {
+    // The flags might have changed since the start.
+    let (emit, output_dir, next_to_originals, dpi) = {
+        let state = state.lock().unwrap();
+
+        (
+            state.get_args().emit,
+            state.get_args().output_dir.clone(),
+            state.get_args().next_to_originals,
+            state.get_args().get_dpi(),
+        )
+    };
+    // The density picked with the wizard takes precedence.
+    let dpi = resized_image.get_dpi().unwrap_or(dpi);
+
+    // Save a copy with the density metadata if an output directory is set,
+    // or next to the original file if requested.
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis();
+    let maybe_path = match (&output_dir, resized_image.get_source_path()) {
+        (_, Some(source_path)) if next_to_originals => Some(get_resized_path(source_path)),
+        (Some(output_dir), _) => Some(get_timestamped_path(output_dir, timestamp)),
+        _ => None,
+    };
+
+    Ok(if resized_image.has_variants() {
+        // The variants are always saved, in the current directory by
+        // default, and referenced by the snippet moved to the clipboard.
+        let path = maybe_path.unwrap_or_else(|| get_timestamped_path(Path::new(""), timestamp));
+
+        match save_variants(resized_image, &path, dpi) {
+            Ok(snippet) => Emit::Html
+                .set_clipboard(clipboard, &snippet)
+                .map(|()| resized_image.set_emitted(Emit::Html))
+                .is_ok(),
+            Err(error) => {
+                report(Level::Error, format!("Saving the variants failed: {error}"));
+
+                false
+            }
+        }
+    } else {
+        if let Some(path) = maybe_path {
+            match save(resized_image.get_buffer(), &path, dpi) {
+                Ok(()) => resized_image.set_saved_path(&path),
+                Err(error) => report(Level::Error, format!("Saving the image failed: {error}")),
+            }
+        }
+
+        match emit {
+            // The snippet replaces the image data, and is flagged as seen.
+            Some(emit) => emit
+                .get_snippet(resized_image.get_buffer(), dpi)
+                .and_then(|snippet| {
+                    emit.set_clipboard(clipboard, &snippet)?;
+                    state.lock().unwrap().set_emitted_snippet(&snippet);
+                    resized_image.set_emitted(emit);
+
+                    Ok(())
+                })
+                .is_ok(),
+            // The clipboard borrows the bytes of the resized image.
+            None => clipboard
+                .set_image(as_image_data(resized_image.get_buffer()))
+                .is_ok(),
+        }
+    })
+}
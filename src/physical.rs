@@ -0,0 +1,167 @@
+use std::convert::TryFrom;
+
+use anyhow::Result;
+
+/// Physical units used for the select prompt.
+static PHYSICAL_UNITS: &[&str; 3] = &["Centimeter", "Millimeter", "Inch"];
+
+/// Suffixes of the physical units, as accepted by the flags.
+static SUFFIXES: &[&str; 3] = &["cm", "mm", "in"];
+
+/// Dots per inch presets used for the select prompt.
+static DPI_PRESETS: &[&str; 3] = &["96 DPI", "150 DPI", "300 DPI"];
+
+/// Values matching the dots per inch presets.
+static DPI_VALUES: &[u16; 3] = &[96, 150, 300];
+
+/// Default dots per inch, matching the common screen density.
+pub(crate) static DEFAULT_DPI: u16 = 96;
+
+/// Enumeration for the physical unit.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PhysicalUnit {
+    /// Centimeters, `cm`.
+    Centimeter = 0,
+    /// Millimeters, `mm`.
+    Millimeter = 1,
+    /// Inches, `in`.
+    Inch = 2,
+}
+
+impl PhysicalUnit {
+    /// Returns the static physical units.
+    pub(crate) fn get_items() -> &'static [&'static str; 3] {
+        PHYSICAL_UNITS
+    }
+
+    /// Returns the suffix associated with the variant.
+    pub(crate) fn get_suffix(self) -> &'static str {
+        SUFFIXES[self as usize]
+    }
+
+    /// Returns how many of the variant fit in one inch.
+    fn per_inch(self) -> f32 {
+        match self {
+            PhysicalUnit::Centimeter => 2.54,
+            PhysicalUnit::Millimeter => 25.4,
+            PhysicalUnit::Inch => 1.,
+        }
+    }
+
+    /// Tries to find the unit matching the suffix.
+    pub(crate) fn from_suffix(suffix: &str) -> Option<Self> {
+        SUFFIXES
+            .iter()
+            .position(|candidate| *candidate == suffix)
+            .and_then(|index| index.try_into().ok())
+    }
+}
+
+impl TryFrom<usize> for PhysicalUnit {
+    type Error = &'static str;
+
+    fn try_from(value: usize) -> Result<Self, Self::Error> {
+        match value {
+            u if u == PhysicalUnit::Centimeter as usize => Ok(PhysicalUnit::Centimeter),
+            u if u == PhysicalUnit::Millimeter as usize => Ok(PhysicalUnit::Millimeter),
+            u if u == PhysicalUnit::Inch as usize => Ok(PhysicalUnit::Inch),
+            // Unreachable.
+            _ => Err("Index cannot be converted to physical unit."),
+        }
+    }
+}
+
+/// Length expressed in a physical unit.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PhysicalLength {
+    value: f32,
+    unit: PhysicalUnit,
+}
+
+impl PhysicalLength {
+    /// Creates a length from a value in the given unit.
+    #[must_use]
+    pub fn new(value: f32, unit: PhysicalUnit) -> Self {
+        Self { value, unit }
+    }
+
+    /// Converts the length to pixels at the given density.
+    /// Note: the result is never smaller than one pixel.
+    #[must_use]
+    pub fn to_pixels(self, dpi: u16) -> u32 {
+        ((self.value / self.unit.per_inch()) * f32::from(dpi))
+            .round()
+            .max(1.) as u32
+    }
+}
+
+/// Returns the static dots per inch presets.
+pub(crate) fn get_dpi_items() -> &'static [&'static str; 3] {
+    DPI_PRESETS
+}
+
+/// Returns the dots per inch matching the preset index.
+pub(crate) fn get_dpi_from_index(index: usize) -> Result<u16, &'static str> {
+    DPI_VALUES
+        .get(index)
+        .copied()
+        .ok_or("Index cannot be converted to DPI.")
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{
+        get_dpi_from_index, get_dpi_items, PhysicalLength, PhysicalUnit, DPI_PRESETS,
+        PHYSICAL_UNITS,
+    };
+
+    #[test]
+    fn check_physical_unit() {
+        assert_eq!(PhysicalUnit::get_items(), PHYSICAL_UNITS);
+
+        let zero_to_unit: PhysicalUnit = 0usize.try_into().unwrap();
+        assert_eq!(zero_to_unit, PhysicalUnit::Centimeter);
+
+        let one_to_unit: PhysicalUnit = 1usize.try_into().unwrap();
+        assert_eq!(one_to_unit, PhysicalUnit::Millimeter);
+
+        let two_to_unit: PhysicalUnit = 2usize.try_into().unwrap();
+        assert_eq!(two_to_unit, PhysicalUnit::Inch);
+
+        assert_eq!(
+            PhysicalUnit::from_suffix("mm"),
+            Some(PhysicalUnit::Millimeter)
+        );
+        assert_eq!(PhysicalUnit::from_suffix("px"), None);
+        assert_eq!(PhysicalUnit::Inch.get_suffix(), "in");
+    }
+
+    #[test]
+    fn check_physical_length_to_pixels() {
+        assert_eq!(
+            PhysicalLength::new(1., PhysicalUnit::Inch).to_pixels(96),
+            96
+        );
+        assert_eq!(
+            PhysicalLength::new(2.54, PhysicalUnit::Centimeter).to_pixels(300),
+            300
+        );
+        assert_eq!(
+            PhysicalLength::new(50.8, PhysicalUnit::Millimeter).to_pixels(150),
+            300
+        );
+        assert_eq!(
+            PhysicalLength::new(0.001, PhysicalUnit::Millimeter).to_pixels(96),
+            1
+        );
+    }
+
+    #[test]
+    fn check_dpi_presets() {
+        assert_eq!(get_dpi_items(), DPI_PRESETS);
+        assert_eq!(get_dpi_from_index(0), Ok(96));
+        assert_eq!(get_dpi_from_index(2), Ok(300));
+        assert!(get_dpi_from_index(3).is_err());
+    }
+}
@@ -0,0 +1,437 @@
+/// Dimensions as a tuple of (height, width).
+pub type DimensionTuple = (u32, u32);
+
+/// Target of the resize operation.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ResizeTarget {
+    /// Dimensions variant as a tuple of (height, width).
+    Dimensions(DimensionTuple),
+    /// High DPI variant with the integer factor to downsample by.
+    HiDpi(u32),
+}
+
+impl ResizeTarget {
+    /// Returns the resized dimensions, as a tuple of (height, width), from the
+    /// original ones.
+    #[must_use]
+    pub fn get_dimensions(self, (height, width): DimensionTuple) -> DimensionTuple {
+        match self {
+            ResizeTarget::Dimensions(dimensions) => dimensions,
+            // The downsampling drops the trailing rows and columns.
+            ResizeTarget::HiDpi(factor) => (
+                (height / factor.max(1)).max(1),
+                (width / factor.max(1)).max(1),
+            ),
+        }
+    }
+}
+
+/// Size specification, i.e. what the resized image should look like whatever
+/// the original dimensions.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SizeSpec {
+    /// Height and / or width in pixels, as a tuple of (height, width).
+    /// A missing dimension preserves the aspect ratio.
+    Pixels(Option<u32>, Option<u32>),
+    /// Height and / or width in pixels, converted from physical lengths at
+    /// the given dots per inch, as a tuple of (height, width, dpi).
+    /// The density is kept for the saved files.
+    Physical(Option<u32>, Option<u32>, u16),
+    /// Height and / or width in percent of the original ones, as a tuple of
+    /// (height, width).
+    /// A missing dimension uses the same percentage as the other one.
+    Percent(Option<u32>, Option<u32>),
+    /// Uniform scale applied to both dimensions.
+    Ratio(f32),
+    /// Budget of pixels that the area of the image can't exceed.
+    PixelCount(u64),
+    /// Maximum byte size, the byte size being assumed proportional to the
+    /// pixel count.
+    MaxByteSize {
+        /// Maximum byte size of the resized image.
+        maximum: u64,
+        /// Byte size of the original image.
+        current: u64,
+    },
+    /// Integer factor to downsample by, for high DPI screenshots.
+    HiDpi(u32),
+    /// Box in pixels, as a tuple of (height, width), that the image fits in
+    /// while preserving its aspect ratio.
+    Fit(u32, u32),
+    /// Box in pixels, as a tuple of (height, width), that the image covers
+    /// while preserving its aspect ratio.
+    Fill(u32, u32),
+}
+
+impl SizeSpec {
+    /// Returns the dots per inch the physical lengths have been converted at,
+    /// if any.
+    #[must_use]
+    pub fn get_dpi(&self) -> Option<u16> {
+        match *self {
+            SizeSpec::Physical(_, _, dpi) => Some(dpi),
+            _ => None,
+        }
+    }
+}
+
+/// Clamps a computed dimension to the range of valid dimensions, from one
+/// pixel to `u32::MAX`.
+fn clamp_dimension(value: u64) -> u32 {
+    u32::try_from(value).unwrap_or(u32::MAX).max(1)
+}
+
+/// Scales a dimension by the fraction numerator / denominator, rounding to the
+/// nearest integer with the halves rounded up.
+/// Note: the maths are done on 128 bits and can't overflow.
+fn scale(value: u32, numerator: u64, denominator: u64) -> u32 {
+    let denominator = u128::from(denominator.max(1));
+    let scaled = (u128::from(value) * u128::from(numerator) + denominator / 2) / denominator;
+
+    clamp_dimension(u64::try_from(scaled).unwrap_or(u64::MAX))
+}
+
+/// Returns the largest dimensions preserving the aspect ratio whose area fits
+/// in the budget.
+/// Note: unlike the other variants, the dimensions are rounded down so that the
+/// budget is never exceeded, unless it's smaller than one pixel.
+fn apply_pixel_budget((height, width): DimensionTuple, budget: u64) -> DimensionTuple {
+    // The width is computed directly, the height being derived from it and
+    // capped so that the area fits when the width is clamped to one pixel.
+    let exact_width = (budget as f64 * f64::from(width) / f64::from(height)).sqrt();
+    let new_width = clamp_dimension(exact_width.floor() as u64);
+    let new_height = clamp_dimension(
+        (u64::from(height) * u64::from(new_width) / u64::from(width))
+            .min(budget / u64::from(new_width)),
+    );
+
+    // The height can be clamped to one pixel, or floating point errors make
+    // the area slightly exceed the budget, the width being reduced once.
+    if u64::from(new_height) * u64::from(new_width) > budget {
+        (new_height, clamp_dimension(budget / u64::from(new_height)))
+    } else {
+        (new_height, new_width)
+    }
+}
+
+/// Returns the dimensions preserving the aspect ratio that fit in the box, or
+/// that cover it, as tuples of (height, width).
+/// Note: the ratios are compared on integers, the limiting dimension being
+/// matched exactly.
+fn apply_box(
+    (height, width): DimensionTuple,
+    (box_height, box_width): DimensionTuple,
+    is_fill: bool,
+) -> DimensionTuple {
+    let (box_height, box_width) = (box_height.max(1), box_width.max(1));
+    let is_width_limiting =
+        u64::from(box_width) * u64::from(height) <= u64::from(box_height) * u64::from(width);
+
+    // The limiting dimension is matched to fit in the box, the other one to
+    // fill it.
+    if is_width_limiting == is_fill {
+        (
+            box_height,
+            scale(width, u64::from(box_height), u64::from(height)),
+        )
+    } else {
+        (
+            scale(height, u64::from(box_width), u64::from(width)),
+            box_width,
+        )
+    }
+}
+
+/// Plans the resize operation of an image from its original dimensions, as a
+/// tuple of (height, width).
+///
+/// The rounding and clamping rules are the same for all the variants:
+/// - dimensions are rounded to the nearest pixel, the halves being rounded up,
+///   except for the pixel count and the maximum byte size which are rounded
+///   down to stay within the budget.
+/// - dimensions are clamped between one pixel and `u32::MAX`, so that the
+///   result is never empty.
+/// - the maths can't overflow, whatever the inputs.
+#[must_use]
+pub fn plan(size_spec: &SizeSpec, (height, width): DimensionTuple) -> ResizeTarget {
+    let (height, width) = (height.max(1), width.max(1));
+
+    let dimensions = match *size_spec {
+        SizeSpec::Pixels(new_height, new_width) | SizeSpec::Physical(new_height, new_width, _) => {
+            match (new_height, new_width) {
+                (Some(new_height), Some(new_width)) => (new_height.max(1), new_width.max(1)),
+                (Some(new_height), None) => (
+                    new_height.max(1),
+                    scale(width, u64::from(new_height), u64::from(height)),
+                ),
+                (None, Some(new_width)) => (
+                    scale(height, u64::from(new_width), u64::from(width)),
+                    new_width.max(1),
+                ),
+                (None, None) => (height, width),
+            }
+        }
+        SizeSpec::Percent(height_percent, width_percent) => {
+            let height_percent = height_percent.or(width_percent).unwrap_or(100);
+            let width_percent = width_percent.unwrap_or(height_percent);
+
+            (
+                scale(height, u64::from(height_percent), 100),
+                scale(width, u64::from(width_percent), 100),
+            )
+        }
+        SizeSpec::Ratio(ratio) => {
+            // Non finite and negative ratios end up as one pixel.
+            let apply_ratio = |dimension: u32| {
+                clamp_dimension((f64::from(dimension) * f64::from(ratio)).round() as u64)
+            };
+
+            (apply_ratio(height), apply_ratio(width))
+        }
+        SizeSpec::PixelCount(budget) => apply_pixel_budget((height, width), budget),
+        // The budget is the share of the pixels fitting in the maximum byte
+        // size.
+        SizeSpec::MaxByteSize { maximum, current } => {
+            let pixel_count = u128::from(height) * u128::from(width);
+            let budget = pixel_count * u128::from(maximum) / u128::from(current.max(1));
+
+            apply_pixel_budget((height, width), u64::try_from(budget).unwrap_or(u64::MAX))
+        }
+        SizeSpec::HiDpi(factor) => return ResizeTarget::HiDpi(factor.max(1)),
+        SizeSpec::Fit(box_height, box_width) => {
+            apply_box((height, width), (box_height, box_width), false)
+        }
+        SizeSpec::Fill(box_height, box_width) => {
+            apply_box((height, width), (box_height, box_width), true)
+        }
+    };
+
+    ResizeTarget::Dimensions(dimensions)
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::{apply_pixel_budget, plan, ResizeTarget, SizeSpec};
+
+    /// Returns the planned dimensions, as a tuple of (height, width).
+    fn plan_dimensions(size_spec: SizeSpec, dimensions: (u32, u32)) -> (u32, u32) {
+        match plan(&size_spec, dimensions) {
+            ResizeTarget::Dimensions(dimensions) => dimensions,
+            ResizeTarget::HiDpi(_) => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn check_apply_pixel_budget() {
+        // Landscape and portrait images reach the same area.
+        assert_eq!(apply_pixel_budget((1080, 1920), 518_400), (540, 960));
+        assert_eq!(apply_pixel_budget((1920, 1080), 518_400), (960, 540));
+
+        // Upscaling is allowed.
+        assert_eq!(apply_pixel_budget((100, 200), 80_000), (200, 400));
+
+        // The area never exceeds the budget.
+        let (height, width) = apply_pixel_budget((1000, 1333), 2_000_000);
+        assert!(u64::from(height) * u64::from(width) <= 2_000_000);
+
+        // Each dimension is at least one pixel.
+        assert_eq!(apply_pixel_budget((10, 1000), 1), (1, 1));
+        assert_eq!(apply_pixel_budget((1, 100_000), 50_000), (1, 50_000));
+        assert_eq!(apply_pixel_budget((100_000, 1), 50_000), (50_000, 1));
+    }
+
+    #[test]
+    fn check_plan() {
+        let size_1080p = (1080, 1920);
+
+        assert_eq!(
+            plan_dimensions(SizeSpec::Pixels(None, Some(960)), size_1080p),
+            (540, 960)
+        );
+        assert_eq!(
+            plan_dimensions(SizeSpec::Percent(Some(50), Some(25)), size_1080p),
+            (540, 480)
+        );
+        assert_eq!(
+            plan_dimensions(SizeSpec::Ratio(0.5), size_1080p),
+            (540, 960)
+        );
+        assert_eq!(
+            plan_dimensions(
+                SizeSpec::MaxByteSize {
+                    maximum: 1080 * 1920,
+                    current: 1080 * 1920 * 4
+                },
+                size_1080p
+            ),
+            (540, 960)
+        );
+        assert_eq!(
+            plan_dimensions(SizeSpec::Fit(600, 800), size_1080p),
+            (450, 800)
+        );
+        assert_eq!(
+            plan_dimensions(SizeSpec::Fill(600, 800), size_1080p),
+            (600, 1067)
+        );
+        assert_eq!(
+            plan_dimensions(SizeSpec::Fit(600, 800), (1920, 1080)),
+            (600, 338)
+        );
+        assert_eq!(
+            plan(&SizeSpec::HiDpi(2), size_1080p),
+            ResizeTarget::HiDpi(2)
+        );
+        assert_eq!(
+            plan(&SizeSpec::HiDpi(2), size_1080p).get_dimensions(size_1080p),
+            (540, 960)
+        );
+    }
+
+    #[test]
+    fn check_plan_rounding() {
+        // Percentages and pixels round the same way, 33% of 1001 being 330.33.
+        assert_eq!(
+            plan_dimensions(SizeSpec::Percent(None, Some(33)), (1001, 1001)),
+            (330, 330)
+        );
+        assert_eq!(
+            plan_dimensions(SizeSpec::Pixels(None, Some(330)), (1001, 1001)),
+            (330, 330)
+        );
+
+        // Halves are rounded up, 50% of 3 being 1.5.
+        assert_eq!(
+            plan_dimensions(SizeSpec::Percent(Some(50), None), (3, 3)),
+            (2, 2)
+        );
+        assert_eq!(
+            plan_dimensions(SizeSpec::Pixels(Some(2), None), (4, 3)),
+            (2, 2)
+        );
+    }
+
+    #[test]
+    fn check_plan_clamping() {
+        // Tiny results are kept at one pixel.
+        assert_eq!(
+            plan_dimensions(SizeSpec::Percent(Some(1), None), (10, 10)),
+            (1, 1)
+        );
+        assert_eq!(
+            plan_dimensions(SizeSpec::Pixels(None, Some(1)), (10, 10_000)),
+            (1, 1)
+        );
+        assert_eq!(plan_dimensions(SizeSpec::Ratio(-1.), (10, 10)), (1, 1));
+        assert_eq!(plan_dimensions(SizeSpec::Ratio(f32::NAN), (10, 10)), (1, 1));
+
+        // Huge results don't overflow.
+        assert_eq!(
+            plan_dimensions(SizeSpec::Percent(Some(u32::MAX), None), (u32::MAX, 1)),
+            (u32::MAX, 42_949_673)
+        );
+        assert_eq!(
+            plan_dimensions(SizeSpec::Ratio(f32::INFINITY), (10, 10)),
+            (u32::MAX, u32::MAX)
+        );
+    }
+
+    /// Original dimensions, from one pixel to a 16K screenshot.
+    fn dimensions() -> impl Strategy<Value = (u32, u32)> {
+        (1..=8640u32, 1..=15_360u32)
+    }
+
+    proptest! {
+        #[test]
+        fn check_plan_never_panics_nor_returns_empty_dimensions(
+            dimensions in (any::<u32>(), any::<u32>()),
+            height in any::<Option<u32>>(),
+            width in any::<Option<u32>>(),
+            ratio in any::<f32>(),
+            budget in any::<u64>(),
+            current in any::<u64>(),
+        ) {
+            let size_specs = [
+                SizeSpec::Pixels(height, width),
+                SizeSpec::Percent(height, width),
+                SizeSpec::Ratio(ratio),
+                SizeSpec::PixelCount(budget),
+                SizeSpec::MaxByteSize { maximum: budget, current },
+                SizeSpec::Fit(height.unwrap_or_default(), width.unwrap_or_default()),
+                SizeSpec::Fill(height.unwrap_or_default(), width.unwrap_or_default()),
+            ];
+
+            for size_spec in size_specs {
+                let (new_height, new_width) = plan_dimensions(size_spec, dimensions);
+
+                prop_assert!(new_height >= 1 && new_width >= 1);
+            }
+        }
+
+        #[test]
+        fn check_plan_pixels_preserves_aspect_ratio(
+            (height, width) in dimensions(),
+            new_width in 1..=15_360u32,
+        ) {
+            let (new_height, planned_width) =
+                plan_dimensions(SizeSpec::Pixels(None, Some(new_width)), (height, width));
+
+            prop_assert_eq!(planned_width, new_width);
+
+            // The height is the closest one to the exact, fractional one.
+            let exact = f64::from(height) * f64::from(new_width) / f64::from(width);
+            prop_assert!((f64::from(new_height) - exact.max(1.)).abs() <= 0.5 + 1e-9);
+        }
+
+        #[test]
+        fn check_plan_percent_matches_ratio(
+            (height, width) in dimensions(),
+            percent in 1..=400u32,
+        ) {
+            // Percentages are exact ratios, rounded the same way.
+            let from_percent = plan_dimensions(SizeSpec::Percent(Some(percent), None), (height, width));
+            let from_ratio = plan_dimensions(
+                SizeSpec::Ratio(percent as f32 / 100.),
+                (height, width),
+            );
+
+            prop_assert!(from_percent.0.abs_diff(from_ratio.0) <= 1);
+            prop_assert!(from_percent.1.abs_diff(from_ratio.1) <= 1);
+        }
+
+        #[test]
+        fn check_plan_pixel_count_fits_in_budget(
+            dimensions in dimensions(),
+            budget in 1..=200_000_000u64,
+        ) {
+            let (new_height, new_width) = plan_dimensions(SizeSpec::PixelCount(budget), dimensions);
+
+            prop_assert!(u64::from(new_height) * u64::from(new_width) <= budget);
+        }
+
+        #[test]
+        fn check_apply_pixel_budget_with_extreme_aspect_ratios(
+            budget in 1..=200_000_000u64,
+        ) {
+            for dimensions in [(1, 100_000), (100_000, 1)] {
+                let (new_height, new_width) = apply_pixel_budget(dimensions, budget);
+
+                prop_assert!(new_height >= 1 && new_width >= 1);
+                prop_assert!(u64::from(new_height) * u64::from(new_width) <= budget);
+            }
+        }
+
+        #[test]
+        fn check_plan_is_monotonic(
+            dimensions in dimensions(),
+            percent in 1..=400u32,
+        ) {
+            let smaller = plan_dimensions(SizeSpec::Percent(Some(percent), None), dimensions);
+            let larger = plan_dimensions(SizeSpec::Percent(Some(percent + 1), None), dimensions);
+
+            prop_assert!(smaller.0 <= larger.0 && smaller.1 <= larger.1);
+        }
+    }
+}
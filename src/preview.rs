@@ -0,0 +1,402 @@
+use std::{collections::BTreeSet, convert::TryFrom, env, fmt::Write, io::Cursor};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use clap::ValueEnum;
+use image::{imageops, ImageOutputFormat, Rgba, RgbaImage};
+
+/// Confirmations used for the select prompt.
+static CONFIRMATIONS: &[&str; 3] = &["Accept", "Re-edit the dimensions", "Cancel"];
+
+/// Maximum number of base64 characters sent per escape sequence of the kitty
+/// graphics protocol.
+static KITTY_CHUNK_SIZE: usize = 4096;
+
+/// Color the transparent pixels are blended with, as half blocks can't be
+/// transparent.
+static BACKGROUND: [u8; 3] = [32, 32, 32];
+
+/// Approximate size of a terminal cell in pixels, as a tuple of (height,
+/// width), used to fit the graphics in the terminal.
+static CELL_SIZE: (u32, u32) = (16, 8);
+
+/// Protocol used to preview the resized images in the terminal.
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub enum Protocol {
+    /// Detected from the environment variables of the terminal, falling back
+    /// to the half blocks.
+    #[default]
+    Auto,
+    /// Kitty graphics protocol, also supported by `WezTerm`.
+    Kitty,
+    /// Sixel graphics, e.g. in foot, mlterm or xterm with `-ti vt340`.
+    Sixel,
+    /// Unicode half blocks with true colors, two pixels per cell.
+    Blocks,
+}
+
+impl Protocol {
+    /// Returns the protocol to use, detecting it if needed.
+    pub(crate) fn resolve(self) -> Self {
+        if self != Protocol::Auto {
+            return self;
+        }
+
+        detect(
+            env::var("TERM").ok().as_deref(),
+            env::var("TERM_PROGRAM").ok().as_deref(),
+            env::var_os("KITTY_WINDOW_ID").is_some(),
+        )
+    }
+}
+
+/// Detects the protocol from the terminal type and program.
+/// Note: sixel support can't be told from the environment for sure, only the
+/// terminals known to support it are picked.
+fn detect(term: Option<&str>, term_program: Option<&str>, is_kitty: bool) -> Protocol {
+    let term = term.unwrap_or_default();
+    let term_program = term_program.unwrap_or_default();
+
+    if is_kitty || term == "xterm-kitty" || ["WezTerm", "ghostty"].contains(&term_program) {
+        return Protocol::Kitty;
+    }
+
+    if term.contains("sixel") || term.starts_with("foot") || term.starts_with("mlterm") {
+        return Protocol::Sixel;
+    }
+
+    Protocol::Blocks
+}
+
+/// Confirmation of the previewed image.
+#[derive(Debug, PartialEq)]
+pub(crate) enum Confirmation {
+    Accept = 0,
+    Edit = 1,
+    Cancel = 2,
+}
+
+impl Confirmation {
+    /// Returns the static confirmations.
+    pub(crate) fn get_items() -> &'static [&'static str; 3] {
+        CONFIRMATIONS
+    }
+}
+
+impl TryFrom<usize> for Confirmation {
+    type Error = &'static str;
+
+    fn try_from(value: usize) -> Result<Self, Self::Error> {
+        match value {
+            u if u == Confirmation::Accept as usize => Ok(Confirmation::Accept),
+            u if u == Confirmation::Edit as usize => Ok(Confirmation::Edit),
+            u if u == Confirmation::Cancel as usize => Ok(Confirmation::Cancel),
+            // Unreachable.
+            _ => Err("Index cannot be converted to confirmation."),
+        }
+    }
+}
+
+/// Returns the preview of the image buffer as escape sequences, fitting in the
+/// terminal size, as a tuple of (rows, columns).
+pub(crate) fn get_preview(
+    protocol: Protocol,
+    image_buffer: &RgbaImage,
+    (rows, columns): (u16, u16),
+) -> String {
+    // Only half of the terminal height is used, to keep the prompt visible.
+    let (rows, columns) = (u32::from(rows / 2).max(1), u32::from(columns).max(1));
+
+    match protocol.resolve() {
+        Protocol::Kitty => encode_kitty(&fit(
+            image_buffer,
+            rows * CELL_SIZE.0,
+            columns * CELL_SIZE.1,
+        )),
+        Protocol::Sixel => encode_sixel(&fit(
+            image_buffer,
+            rows * CELL_SIZE.0,
+            columns * CELL_SIZE.1,
+        )),
+        // Each cell holds two pixels, one above the other.
+        Protocol::Auto | Protocol::Blocks => encode_blocks(&fit(image_buffer, rows * 2, columns)),
+    }
+}
+
+/// Downsizes the image buffer to fit in the maximum dimensions, preserving
+/// the aspect ratio.
+fn fit(image_buffer: &RgbaImage, max_height: u32, max_width: u32) -> RgbaImage {
+    let (width, height) = image_buffer.dimensions();
+
+    if width <= max_width && height <= max_height {
+        return image_buffer.clone();
+    }
+
+    let factor = f64::min(
+        f64::from(max_width) / f64::from(width),
+        f64::from(max_height) / f64::from(height),
+    );
+
+    imageops::thumbnail(
+        image_buffer,
+        ((f64::from(width) * factor) as u32).max(1),
+        ((f64::from(height) * factor) as u32).max(1),
+    )
+}
+
+/// Blends a pixel with the background, returning its color.
+fn blend(Rgba([red, green, blue, alpha]): Rgba<u8>) -> [u8; 3] {
+    let alpha = u16::from(alpha);
+    let mix = |channel: u8, background: u8| {
+        ((u16::from(channel) * alpha + u16::from(background) * (255 - alpha)) / 255) as u8
+    };
+
+    [
+        mix(red, BACKGROUND[0]),
+        mix(green, BACKGROUND[1]),
+        mix(blue, BACKGROUND[2]),
+    ]
+}
+
+/// Encodes the image buffer with the kitty graphics protocol, as a PNG sent
+/// in chunks.
+fn encode_kitty(image_buffer: &RgbaImage) -> String {
+    let mut bytes = Vec::new();
+
+    // Encoding to memory can't fail.
+    let _ = image_buffer.write_to(&mut Cursor::new(&mut bytes), ImageOutputFormat::Png);
+
+    let payload = STANDARD.encode(bytes);
+    let chunks = payload
+        .as_bytes()
+        .chunks(KITTY_CHUNK_SIZE)
+        .collect::<Vec<_>>();
+    let mut output = String::new();
+
+    for (index, chunk) in chunks.iter().enumerate() {
+        // The control data is only needed by the first chunk.
+        let control = if index == 0 { "a=T,f=100," } else { "" };
+        let more = u8::from(index + 1 < chunks.len());
+
+        // The chunks are made of base64 characters.
+        let chunk = String::from_utf8_lossy(chunk);
+
+        let _ = write!(output, "\x1b_G{control}m={more};{chunk}\x1b\\");
+    }
+
+    output.push('\n');
+
+    output
+}
+
+/// Returns the level, from 0 to 5, of a channel in the 6×6×6 color cube.
+fn get_level(channel: u8) -> u16 {
+    (u16::from(channel) * 5 + 127) / 255
+}
+
+/// Encodes the image buffer as sixel graphics, the colors being quantized to
+/// a 6×6×6 cube and the transparent pixels left untouched.
+fn encode_sixel(image_buffer: &RgbaImage) -> String {
+    let (width, height) = image_buffer.dimensions();
+    let get_color = |x: u32, y: u32| {
+        let Rgba([red, green, blue, alpha]) = *image_buffer.get_pixel(x, y);
+
+        (alpha >= 128).then(|| get_level(red) * 36 + get_level(green) * 6 + get_level(blue))
+    };
+
+    let mut output = format!("\x1bP0;1q\"1;1;{width};{height}");
+
+    for color in 0..216 {
+        let _ = write!(
+            output,
+            "#{color};2;{};{};{}",
+            color / 36 * 20,
+            color / 6 % 6 * 20,
+            color % 6 * 20
+        );
+    }
+
+    // Each band is six pixels high, each color of the band being drawn in its
+    // own pass.
+    for top in (0..height).step_by(6) {
+        let rows = top..(top + 6).min(height);
+        let colors = (0..width)
+            .flat_map(|x| rows.clone().filter_map(move |y| get_color(x, y)))
+            .collect::<BTreeSet<_>>();
+
+        for color in colors {
+            let sixels = (0..width).map(|x| {
+                let bits = rows
+                    .clone()
+                    .filter(|&y| get_color(x, y) == Some(color))
+                    .fold(0, |bits, y| bits | 1 << (y - top));
+
+                char::from(63 + bits as u8)
+            });
+
+            let _ = write!(output, "#{color}");
+            push_run_lengths(&mut output, sixels);
+            output.push('$');
+        }
+
+        output.push('-');
+    }
+
+    output.push_str("\x1b\\\n");
+
+    output
+}
+
+/// Pushes the sixels with their repetitions run-length encoded.
+fn push_run_lengths(output: &mut String, sixels: impl Iterator<Item = char>) {
+    let mut sixels = sixels.peekable();
+
+    while let Some(sixel) = sixels.next() {
+        let mut count = 1;
+
+        while sixels.next_if_eq(&sixel).is_some() {
+            count += 1;
+        }
+
+        if count > 3 {
+            let _ = write!(output, "!{count}{sixel}");
+        } else {
+            output.extend((0..count).map(|_| sixel));
+        }
+    }
+}
+
+/// Encodes the image buffer as Unicode half blocks, the upper pixel being the
+/// foreground color and the lower one the background color.
+fn encode_blocks(image_buffer: &RgbaImage) -> String {
+    let (width, height) = image_buffer.dimensions();
+    let mut output = String::new();
+
+    for top in (0..height).step_by(2) {
+        for x in 0..width {
+            let [red, green, blue] = blend(*image_buffer.get_pixel(x, top));
+            let _ = write!(output, "\x1b[38;2;{red};{green};{blue}m");
+
+            // The last row of an odd height has no lower pixel.
+            if top + 1 < height {
+                let [red, green, blue] = blend(*image_buffer.get_pixel(x, top + 1));
+                let _ = write!(output, "\x1b[48;2;{red};{green};{blue}m");
+            } else {
+                output.push_str("\x1b[49m");
+            }
+
+            output.push('▀');
+        }
+
+        output.push_str("\x1b[0m\n");
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgba, RgbaImage};
+
+    use super::{
+        detect, encode_blocks, encode_kitty, encode_sixel, fit, get_preview, Confirmation,
+        Protocol, CONFIRMATIONS,
+    };
+
+    #[test]
+    fn check_detect() {
+        assert_eq!(detect(Some("xterm-kitty"), None, false), Protocol::Kitty);
+        assert_eq!(detect(Some("xterm-256color"), None, true), Protocol::Kitty);
+        assert_eq!(
+            detect(Some("xterm-256color"), Some("WezTerm"), false),
+            Protocol::Kitty
+        );
+        assert_eq!(detect(Some("foot"), None, false), Protocol::Sixel);
+        assert_eq!(
+            detect(Some("xterm-256color"), None, false),
+            Protocol::Blocks
+        );
+        assert_eq!(detect(None, None, false), Protocol::Blocks);
+
+        assert_eq!(Protocol::Sixel.resolve(), Protocol::Sixel);
+    }
+
+    #[test]
+    fn check_confirmation() {
+        assert_eq!(Confirmation::get_items(), CONFIRMATIONS);
+
+        let two_to_confirmation: Confirmation = 2usize.try_into().unwrap();
+        assert_eq!(two_to_confirmation, Confirmation::Cancel);
+
+        assert!(Confirmation::try_from(3usize).is_err());
+    }
+
+    #[test]
+    fn check_fit() {
+        let image_buffer = RgbaImage::new(400, 100);
+
+        assert_eq!(fit(&image_buffer, 50, 100).dimensions(), (100, 25));
+        assert_eq!(fit(&image_buffer, 10, 1000).dimensions(), (40, 10));
+        assert_eq!(fit(&image_buffer, 200, 800).dimensions(), (400, 100));
+    }
+
+    #[test]
+    fn check_encode_blocks() {
+        let mut image_buffer = RgbaImage::new(2, 3);
+        image_buffer.put_pixel(0, 0, Rgba([255, 0, 0, 255]));
+        image_buffer.put_pixel(0, 1, Rgba([0, 0, 255, 255]));
+
+        let blocks = encode_blocks(&image_buffer);
+
+        assert_eq!(blocks.lines().count(), 2);
+        assert_eq!(blocks.matches('▀').count(), 4);
+        assert!(blocks.starts_with("\x1b[38;2;255;0;0m\x1b[48;2;0;0;255m▀"));
+        // Transparent pixels are blended with the background.
+        assert!(blocks.contains("\x1b[38;2;32;32;32m\x1b[48;2;32;32;32m▀"));
+        // The last row has no lower pixel.
+        assert!(blocks.ends_with("\x1b[49m▀\x1b[0m\n"));
+    }
+
+    #[test]
+    fn check_encode_sixel() {
+        let mut image_buffer = RgbaImage::new(5, 7);
+        for x in 0..5 {
+            image_buffer.put_pixel(x, 0, Rgba([255, 255, 255, 255]));
+        }
+
+        let sixel = encode_sixel(&image_buffer);
+
+        assert!(sixel.starts_with("\x1bP0;1q\"1;1;5;7"));
+        assert!(sixel.contains("#215;2;100;100;100"));
+        // Only the opaque top row is drawn, in the first band.
+        assert!(sixel.ends_with("#215!5@$--\x1b\\\n"));
+    }
+
+    #[test]
+    fn check_encode_kitty() {
+        let kitty = encode_kitty(&RgbaImage::new(100, 100));
+
+        assert!(kitty.starts_with("\x1b_Ga=T,f=100,m=0;iVBORw0KGgo"));
+        assert!(kitty.ends_with("\x1b\\\n"));
+
+        // Large payloads are chunked.
+        let mut image_buffer = RgbaImage::new(200, 200);
+        for (x, y, pixel) in image_buffer.enumerate_pixels_mut() {
+            *pixel = Rgba([(x * 7) as u8, (y * 13) as u8, (x * y) as u8, 255]);
+        }
+
+        let kitty = encode_kitty(&image_buffer);
+
+        assert!(kitty.starts_with("\x1b_Ga=T,f=100,m=1;"));
+        assert!(kitty.contains("\x1b_Gm=1;"));
+        assert!(kitty.contains("\x1b_Gm=0;"));
+    }
+
+    #[test]
+    fn check_get_preview() {
+        let preview = get_preview(Protocol::Blocks, &RgbaImage::new(400, 100), (20, 40));
+
+        // The image is fitted in 40 columns, two pixels per row.
+        assert_eq!(preview.lines().count(), 5);
+        assert_eq!(preview.matches('▀').count(), 200);
+    }
+}
@@ -0,0 +1,64 @@
+use std::{
+    fmt::Display,
+    sync::{mpsc::Sender, Mutex},
+};
+
+use dialoguer::console::style;
+
+use crate::assets::BOOM;
+
+/// Receiver of the reported messages instead of the terminal, e.g. the
+/// dashboard.
+static REDIRECTION: Mutex<Option<Sender<(Level, String)>>> = Mutex::new(None);
+
+/// Severity of a reported message.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Level {
+    /// Progress of the processing.
+    Info,
+    /// Skipped images.
+    Warning,
+    /// Failures.
+    Error,
+}
+
+/// Redirects the reported messages to the sender instead of the terminal, or
+/// back to the terminal.
+///
+/// # Panics
+///
+/// Panics if the redirection is poisoned.
+pub fn redirect(sender: Option<Sender<(Level, String)>>) {
+    *REDIRECTION.lock().unwrap() = sender;
+}
+
+/// Checks if the reported messages are redirected, in which case nothing
+/// else should be drawn on the terminal.
+///
+/// # Panics
+///
+/// Panics if the redirection is poisoned.
+#[must_use]
+pub fn is_redirected() -> bool {
+    REDIRECTION.lock().unwrap().is_some()
+}
+
+/// Reports a message to the user, printed on the terminal unless redirected.
+///
+/// # Panics
+///
+/// Panics if the redirection is poisoned.
+pub fn report(level: Level, message: impl Display) {
+    if let Some(sender) = &*REDIRECTION.lock().unwrap() {
+        // The receiver might be gone already.
+        let _ = sender.send((level, message.to_string()));
+
+        return;
+    }
+
+    match level {
+        Level::Info => println!("{}", style(message).dim()),
+        Level::Warning => eprintln!("{}", style(message).yellow()),
+        Level::Error => eprintln!("{}{}", style(message).red(), BOOM),
+    }
+}
@@ -0,0 +1,337 @@
+use clap::ValueEnum;
+use image::{imageops, ImageBuffer, Pixel, Rgb, Rgba, RgbaImage};
+
+#[cfg(feature = "fast-resize")]
+use crate::fast_resize;
+use crate::{
+    alpha::{
+        decode_channel, encode_channel, flatten, has_transparency, premultiply, strip_alpha,
+        unpremultiply,
+    },
+    args::Args,
+    hidpi::{downsample, Channel},
+    linear::{to_linear, to_srgb},
+    planner::ResizeTarget,
+};
+
+/// Backend used for the resize operation.
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub enum ResizeBackend {
+    /// The `image` crate.
+    #[default]
+    Image,
+    /// The `fast_image_resize` crate, using SIMD instructions on all the
+    /// cores.
+    /// Only used for dimensions in sRGB, the alpha channel being
+    /// premultiplied at 8-bit precision.
+    #[cfg(feature = "fast-resize")]
+    Fast,
+}
+
+impl ResizeBackend {
+    /// Resizes the image buffer to the target with the backend, taking care
+    /// of the alpha channel on its own, or returns `None` if it's left to the
+    /// `image` crate.
+    #[cfg_attr(not(feature = "fast-resize"), allow(unused_variables))]
+    fn resize(
+        self,
+        image_buffer: &RgbaImage,
+        target: &ResizeTarget,
+        use_alpha: bool,
+    ) -> Option<RgbaImage> {
+        // High DPI targets are downsampled by an exact integer factor, which
+        // is left to the `image` crate.
+        match (self, target) {
+            #[cfg(feature = "fast-resize")]
+            (ResizeBackend::Fast, ResizeTarget::Dimensions((height, width))) => {
+                fast_resize::resize(image_buffer, *width, *height, use_alpha)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Options applied around the resize operation.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ResizeOptions {
+    /// Background color to flatten the image onto.
+    pub flatten: Option<Rgb<u8>>,
+    /// Whether the alpha channel is dropped.
+    pub strip_alpha: bool,
+    /// Whether the image is resampled in linear light.
+    pub linear: bool,
+    /// Backend used for the resize operation.
+    pub backend: ResizeBackend,
+}
+
+impl From<&Args> for ResizeOptions {
+    fn from(args: &Args) -> Self {
+        Self {
+            flatten: args.flatten,
+            strip_alpha: args.strip_alpha,
+            linear: args.linear,
+            backend: args.backend,
+        }
+    }
+}
+
+/// Resamples the image buffer to the target, whatever its channel type.
+fn resample<T: Channel>(
+    image_buffer: &ImageBuffer<Rgba<T>, Vec<T>>,
+    target: &ResizeTarget,
+) -> ImageBuffer<Rgba<T>, Vec<T>>
+where
+    Rgba<T>: Pixel<Subpixel = T>,
+{
+    // High DPI images are downsampled by an exact integer factor.
+    match *target {
+        ResizeTarget::Dimensions((height, width)) => {
+            imageops::resize(image_buffer, width, height, imageops::FilterType::Lanczos3)
+        }
+        ResizeTarget::HiDpi(factor) => downsample(image_buffer, factor),
+    }
+}
+
+/// Resizes the image buffer to the target.
+/// Transparent images are resampled with premultiplied alpha to avoid dark
+/// fringes at their edges.
+#[must_use]
+pub fn resize_image(
+    image_buffer: RgbaImage,
+    target: &ResizeTarget,
+    options: &ResizeOptions,
+) -> RgbaImage {
+    resize_images(image_buffer, &[target], options)
+        .pop()
+        .unwrap_or_default()
+}
+
+/// Resizes the image buffer to each of the targets, in the same order.
+/// The image buffer is prepared once, e.g. flattened or premultiplied, and
+/// borrowed by all the targets.
+/// Note: the premultiplication is done at float precision, along with the
+/// linear light conversion if requested.
+#[must_use]
+pub fn resize_images(
+    mut image_buffer: RgbaImage,
+    targets: &[&ResizeTarget],
+    options: &ResizeOptions,
+) -> Vec<RgbaImage> {
+    if let Some(background) = options.flatten {
+        flatten(&mut image_buffer, background);
+    } else if options.strip_alpha {
+        strip_alpha(&mut image_buffer);
+    }
+
+    resize_prepared_images(&image_buffer, targets, options)
+}
+
+/// Resizes the borrowed image buffer to each of the targets, in the same
+/// order, so that it can be kept as is, e.g. as the original to restore.
+/// Note: the image buffer is only copied if it has to be flattened or
+/// stripped of its alpha channel.
+pub(crate) fn resize_borrowed_images(
+    image_buffer: &RgbaImage,
+    targets: &[&ResizeTarget],
+    options: &ResizeOptions,
+) -> Vec<RgbaImage> {
+    if options.flatten.is_some() || options.strip_alpha {
+        return resize_images(image_buffer.clone(), targets, options);
+    }
+
+    resize_prepared_images(image_buffer, targets, options)
+}
+
+/// Resizes the image buffer, already flattened or stripped of its alpha
+/// channel if requested, to each of the targets.
+fn resize_prepared_images(
+    image_buffer: &RgbaImage,
+    targets: &[&ResizeTarget],
+    options: &ResizeOptions,
+) -> Vec<RgbaImage> {
+    // The linear light conversion takes care of the premultiplication.
+    if options.linear {
+        let linear_buffer = to_linear(image_buffer);
+
+        return targets
+            .iter()
+            .map(|target| to_srgb(&resample(&linear_buffer, target)))
+            .collect();
+    }
+
+    // Opaque images don't need the extra passes, the premultiplied buffer
+    // being only created if a target is left to the `image` crate.
+    let is_transparent = has_transparency(image_buffer);
+    let mut premultiplied_buffer = None;
+    let backend = options.backend;
+
+    targets
+        .iter()
+        .map(|target| {
+            if let Some(resized_buffer) = backend.resize(image_buffer, target, is_transparent) {
+                return resized_buffer;
+            }
+
+            if !is_transparent {
+                return resample(image_buffer, target);
+            }
+
+            let premultiplied_buffer = premultiplied_buffer
+                .get_or_insert_with(|| premultiply(image_buffer, decode_channel));
+
+            unpremultiply(&resample(premultiplied_buffer, target), encode_channel)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgb, Rgba, RgbaImage};
+
+    use super::{resize_borrowed_images, resize_image, resize_images, ResizeOptions};
+    use crate::planner::ResizeTarget;
+
+    #[test]
+    fn check_resize_image_dimensions() {
+        let image_buffer = RgbaImage::from_pixel(40, 20, Rgba([1, 2, 3, 255]));
+
+        let resized = resize_image(
+            image_buffer.clone(),
+            &ResizeTarget::Dimensions((10, 30)),
+            &ResizeOptions::default(),
+        );
+        assert_eq!(resized.dimensions(), (30, 10));
+
+        let resized = resize_image(
+            image_buffer,
+            &ResizeTarget::HiDpi(2),
+            &ResizeOptions::default(),
+        );
+        assert_eq!(resized.dimensions(), (20, 10));
+    }
+
+    #[test]
+    fn check_resize_image_linear() {
+        // One pixel wide black and white stripes.
+        let image_buffer = RgbaImage::from_fn(8, 8, |x, _| {
+            if x % 2 == 0 {
+                Rgba([0, 0, 0, 255])
+            } else {
+                Rgba([255, 255, 255, 255])
+            }
+        });
+        let options = ResizeOptions {
+            linear: true,
+            ..ResizeOptions::default()
+        };
+
+        let resized = resize_image(image_buffer.clone(), &ResizeTarget::HiDpi(2), &options);
+        assert!(resized
+            .pixels()
+            .all(|pixel| *pixel == Rgba([188, 188, 188, 255])));
+
+        let resized = resize_image(
+            image_buffer,
+            &ResizeTarget::HiDpi(2),
+            &ResizeOptions::default(),
+        );
+        assert!(resized
+            .pixels()
+            .all(|pixel| *pixel == Rgba([128, 128, 128, 255])));
+    }
+
+    #[test]
+    fn check_resize_image_flatten() {
+        let image_buffer = RgbaImage::from_pixel(4, 4, Rgba([0, 0, 0, 0]));
+        let options = ResizeOptions {
+            flatten: Some(Rgb([255, 255, 255])),
+            ..ResizeOptions::default()
+        };
+
+        let resized = resize_image(image_buffer, &ResizeTarget::HiDpi(2), &options);
+
+        assert!(resized
+            .pixels()
+            .all(|pixel| *pixel == Rgba([255, 255, 255, 255])));
+    }
+
+    #[test]
+    fn check_resize_image_strip_alpha() {
+        let image_buffer = RgbaImage::from_pixel(4, 4, Rgba([10, 20, 30, 0]));
+        let options = ResizeOptions {
+            strip_alpha: true,
+            ..ResizeOptions::default()
+        };
+
+        let resized = resize_image(image_buffer, &ResizeTarget::HiDpi(2), &options);
+
+        assert!(resized
+            .pixels()
+            .all(|pixel| *pixel == Rgba([10, 20, 30, 255])));
+    }
+
+    #[test]
+    fn check_resize_images() {
+        let image_buffer = RgbaImage::from_fn(40, 20, |x, _| {
+            if x < 20 {
+                Rgba([255, 255, 255, 255])
+            } else {
+                Rgba([0, 0, 0, 0])
+            }
+        });
+        let targets = [ResizeTarget::Dimensions((5, 10)), ResizeTarget::HiDpi(2)];
+        let options = ResizeOptions::default();
+
+        let resized_buffers =
+            resize_images(image_buffer.clone(), &[&targets[0], &targets[1]], &options);
+
+        // Same results as the targets resized on their own.
+        assert_eq!(resized_buffers.len(), 2);
+        for (resized_buffer, target) in resized_buffers.iter().zip(&targets) {
+            assert_eq!(
+                *resized_buffer,
+                resize_image(image_buffer.clone(), target, &options)
+            );
+        }
+
+        // Same results when borrowed, even if the image is flattened.
+        for options in [
+            options,
+            ResizeOptions {
+                flatten: Some(Rgb([255, 0, 0])),
+                ..ResizeOptions::default()
+            },
+        ] {
+            assert_eq!(
+                resize_borrowed_images(&image_buffer, &[&targets[0], &targets[1]], &options),
+                resize_images(image_buffer.clone(), &[&targets[0], &targets[1]], &options)
+            );
+        }
+    }
+
+    #[cfg(feature = "fast-resize")]
+    #[test]
+    fn check_resize_image_fast_has_no_dark_fringe() {
+        use crate::resize::ResizeBackend;
+
+        // Opaque white next to fully transparent black.
+        let image_buffer = RgbaImage::from_fn(8, 1, |x, _| {
+            if x < 4 {
+                Rgba([255, 255, 255, 255])
+            } else {
+                Rgba([0, 0, 0, 0])
+            }
+        });
+        let options = ResizeOptions {
+            backend: ResizeBackend::Fast,
+            ..ResizeOptions::default()
+        };
+
+        let resized = resize_image(image_buffer, &ResizeTarget::Dimensions((1, 4)), &options);
+
+        assert!(resized
+            .pixels()
+            .filter(|pixel| pixel[3] > 0)
+            .all(|pixel| pixel[0] >= 250));
+    }
+}
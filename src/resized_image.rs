@@ -1,20 +1,32 @@
+use std::path::{Path, PathBuf};
+
 use dialoguer::console::style;
 use image::{ImageBuffer, Rgba};
 use indicatif::{BinaryBytes, HumanDuration};
-use tokio::time::Instant;
+use tokio::time::{Duration, Instant};
 
-use crate::assets::{CLIPBOARD, HEIGHT, STATS, WIDTH, ZAP};
+use crate::{
+    assets::{CLIPBOARD, FLOPPY, HEIGHT, STATS, WIDTH, ZAP},
+    snippet::Emit,
+};
 
 /// Simple type alias.
 type ImageBufferU8 = ImageBuffer<Rgba<u8>, Vec<u8>>;
 
 /// Item produced by the stream.
 /// Note: The fields are kept private and exposed by the implementations.
-pub(crate) struct ResizedImage {
+#[derive(Debug)]
+pub struct ResizedImage {
+    descriptor: Option<String>,
+    dpi: Option<u16>,
+    emitted: Option<Emit>,
     image_buffer: ImageBufferU8,
     original_height: u32,
     original_width: u32,
+    saved_paths: Vec<PathBuf>,
+    source_path: Option<PathBuf>,
     start_time: Instant,
+    variants: Vec<(String, ImageBufferU8)>,
 }
 
 impl ResizedImage {
@@ -22,21 +34,124 @@ impl ResizedImage {
         image_buffer: ImageBufferU8,
         original_height: u32,
         original_width: u32,
+        source_path: Option<PathBuf>,
         start_time: Instant,
     ) -> Self {
         Self {
+            descriptor: None,
+            dpi: None,
+            emitted: None,
             image_buffer,
             original_height,
             original_width,
+            saved_paths: Vec::new(),
+            source_path,
             start_time,
+            variants: Vec::new(),
         }
     }
 
-    pub(crate) fn get_buffer(&mut self) -> &ImageBufferU8 {
+    /// Adds the other variants of the image, from the smallest to the largest
+    /// one, the resized image being the largest.
+    pub(crate) fn with_variants(
+        mut self,
+        descriptor: Option<String>,
+        variants: Vec<(String, ImageBufferU8)>,
+    ) -> Self {
+        self.descriptor = descriptor;
+        self.variants = variants;
+
+        self
+    }
+
+    /// Sets the dots per inch picked along with the size specification, if
+    /// any.
+    pub(crate) fn with_dpi(mut self, dpi: Option<u16>) -> Self {
+        self.dpi = dpi;
+
+        self
+    }
+
+    /// Returns the dots per inch picked along with the size specification,
+    /// if any, used instead of the flags when saving the image.
+    #[must_use]
+    pub fn get_dpi(&self) -> Option<u16> {
+        self.dpi
+    }
+
+    /// Returns the resized image buffer.
+    #[must_use]
+    pub fn get_buffer(&self) -> &ImageBufferU8 {
         &self.image_buffer
     }
 
-    pub(crate) fn get_stats(&mut self) {
+    /// Checks if the image comes with responsive variants.
+    #[must_use]
+    pub fn has_variants(&self) -> bool {
+        self.descriptor.is_some()
+    }
+
+    /// Returns the responsive variants along with their descriptors, e.g.
+    /// `640w` or `2x`, from the smallest to the largest one.
+    pub fn get_variants(&self) -> impl Iterator<Item = (&str, &ImageBufferU8)> {
+        self.variants
+            .iter()
+            .map(|(descriptor, image_buffer)| (descriptor.as_str(), image_buffer))
+            .chain(
+                self.descriptor
+                    .as_deref()
+                    .map(|descriptor| (descriptor, &self.image_buffer)),
+            )
+    }
+
+    /// Returns the original dimensions, as a tuple of (width, height).
+    #[must_use]
+    pub fn get_original_dimensions(&self) -> (u32, u32) {
+        (self.original_width, self.original_height)
+    }
+
+    /// Returns the time elapsed since the start of the resize operation.
+    #[must_use]
+    pub fn get_elapsed(&self) -> Duration {
+        self.start_time.elapsed()
+    }
+
+    /// Returns the snippet moved to the clipboard instead of the image, if
+    /// any.
+    #[must_use]
+    pub fn get_emitted(&self) -> Option<Emit> {
+        self.emitted
+    }
+
+    /// Returns the path of the original file, for the images copied as files.
+    #[must_use]
+    pub fn get_source_path(&self) -> Option<&Path> {
+        self.source_path.as_deref()
+    }
+
+    /// Returns the paths where the image, and its variants, have been saved.
+    #[must_use]
+    pub fn get_saved_paths(&self) -> &[PathBuf] {
+        &self.saved_paths
+    }
+
+    /// Keeps track of a path where the image, or one of its variants, has
+    /// been saved.
+    pub fn set_saved_path(&mut self, path: &Path) {
+        self.saved_paths.push(path.to_path_buf());
+    }
+
+    /// Keeps track of the snippet moved to the clipboard instead of the image.
+    pub fn set_emitted(&mut self, emit: Emit) {
+        self.emitted = Some(emit);
+    }
+
+    /// Prints the statistics of the resize operation.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the byte size doesn't fit in a `u64`.
+    pub fn get_stats(&mut self) {
         let print_dimension = |dimension: u32| style(format!("{}{}", dimension, "px")).magenta();
 
         println!(
@@ -65,7 +180,21 @@ impl ResizedImage {
             ))
             .magenta()
         );
-        println!("{CLIPBOARD}Resized image successfully moved to the clipboard.",);
+        for saved_path in &self.saved_paths {
+            println!(
+                "{}Saved to {}.",
+                FLOPPY,
+                style(saved_path.display()).magenta()
+            );
+        }
+        match self.emitted {
+            Some(emit) => println!(
+                "{}Resized image successfully moved to the clipboard as {}.",
+                CLIPBOARD,
+                emit.get_description()
+            ),
+            None => println!("{CLIPBOARD}Resized image successfully moved to the clipboard."),
+        }
 
         // New line for readability.
         println!();
@@ -0,0 +1,25 @@
+use std::{error::Error, fmt};
+
+/// Error reported when an image is skipped rather than processed, the stream
+/// going on with the next images.
+#[derive(Debug, PartialEq)]
+pub struct Skip {
+    reason: String,
+}
+
+impl Skip {
+    /// Creates the error from the reason of the skip.
+    pub fn new(reason: impl Into<String>) -> Self {
+        Self {
+            reason: reason.into(),
+        }
+    }
+}
+
+impl fmt::Display for Skip {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Image skipped: {}", self.reason)
+    }
+}
+
+impl Error for Skip {}
@@ -0,0 +1,166 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use arboard::Clipboard;
+use clap::ValueEnum;
+use image::RgbaImage;
+
+use crate::data_uri::encode_data_uri;
+
+/// Text snippet moved to the clipboard instead of the image data.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum Emit {
+    /// Data URI, e.g. `data:image/png;base64,...`.
+    DataUri,
+    /// Markdown image, e.g. `![](data:image/png;base64,...)`.
+    Markdown,
+    /// HTML image element with the dimensions, e.g.
+    /// `<img width="960" height="540" src="data:image/png;base64,...">`.
+    Html,
+}
+
+impl Emit {
+    /// Returns the snippet embedding the image buffer, encoded as PNG with
+    /// density metadata.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the encoding fails.
+    pub fn get_snippet(self, image_buffer: &RgbaImage, dpi: u16) -> Result<String> {
+        let data_uri = encode_data_uri(image_buffer, dpi)?;
+
+        Ok(match self {
+            Emit::DataUri => data_uri,
+            Emit::Markdown => format!("![]({data_uri})"),
+            Emit::Html => format!(
+                r#"<img width="{}" height="{}" src="{}">"#,
+                image_buffer.width(),
+                image_buffer.height(),
+                data_uri
+            ),
+        })
+    }
+
+    /// Moves the snippet to the clipboard.
+    /// Note: the HTML element is moved as both rich and plain text, so that
+    /// it can be pasted in emails as well as in code editors.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the clipboard can't be written.
+    pub fn set_clipboard(self, clipboard: &mut Clipboard, snippet: &str) -> Result<()> {
+        match self {
+            Emit::Html => clipboard.set_html(snippet, Some(snippet))?,
+            Emit::DataUri | Emit::Markdown => clipboard.set_text(snippet)?,
+        }
+
+        Ok(())
+    }
+
+    /// Returns the description of the snippet, for the statistics.
+    #[must_use]
+    pub fn get_description(self) -> &'static str {
+        match self {
+            Emit::DataUri => "a data URI",
+            Emit::Markdown => "a Markdown image",
+            Emit::Html => "an HTML image element",
+        }
+    }
+}
+
+/// Returns the HTML image element referencing the variants in its `srcset`
+/// attribute, as tuples of (descriptor, path, (width, height)) from the
+/// smallest to the largest one.
+/// The smallest variant is the fallback source, and gives the dimensions.
+/// Note: the paths are expected to be next to the page, only the file names
+/// being kept.
+#[must_use]
+pub fn get_srcset_snippet(sources: &[(String, PathBuf, (u32, u32))]) -> String {
+    let get_file_name = |path: &PathBuf| {
+        path.file_name()
+            .map(|file_name| file_name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    };
+
+    let srcset = sources
+        .iter()
+        .map(|(descriptor, path, _)| format!("{} {}", get_file_name(path), descriptor))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    // The width descriptors need the slot size, assumed to be the viewport.
+    let sizes = if sources
+        .iter()
+        .any(|(descriptor, _, _)| descriptor.ends_with('w'))
+    {
+        r#" sizes="100vw""#
+    } else {
+        ""
+    };
+
+    let Some((_, path, (width, height))) = sources.first() else {
+        return String::new();
+    };
+
+    format!(
+        r#"<img src="{}" srcset="{}"{} width="{}" height="{}">"#,
+        get_file_name(path),
+        srcset,
+        sizes,
+        width,
+        height
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use image::{Rgba, RgbaImage};
+
+    use super::{get_srcset_snippet, Emit};
+
+    #[test]
+    fn check_get_snippet() {
+        let image_buffer = RgbaImage::from_pixel(3, 2, Rgba([255, 0, 0, 255]));
+
+        let data_uri = Emit::DataUri.get_snippet(&image_buffer, 96).unwrap();
+        assert!(data_uri.starts_with("data:image/png;base64,iVBORw0KGgo"));
+
+        assert_eq!(
+            Emit::Markdown.get_snippet(&image_buffer, 96).unwrap(),
+            format!("![]({data_uri})")
+        );
+        assert_eq!(
+            Emit::Html.get_snippet(&image_buffer, 96).unwrap(),
+            format!(r#"<img width="3" height="2" src="{data_uri}">"#)
+        );
+    }
+
+    #[test]
+    fn check_get_srcset_snippet() {
+        assert_eq!(
+            get_srcset_snippet(&[
+                (
+                    String::from("1x"),
+                    PathBuf::from("/tmp/shot-1x.png"),
+                    (480, 270)
+                ),
+                (
+                    String::from("2x"),
+                    PathBuf::from("/tmp/shot-2x.png"),
+                    (960, 540)
+                ),
+            ]),
+            r#"<img src="shot-1x.png" srcset="shot-1x.png 1x, shot-2x.png 2x" width="480" height="270">"#
+        );
+        assert_eq!(
+            get_srcset_snippet(&[(
+                String::from("320w"),
+                PathBuf::from("shot-320w.png"),
+                (320, 180)
+            )]),
+            r#"<img src="shot-320w.png" srcset="shot-320w.png 320w" sizes="100vw" width="320" height="180">"#
+        );
+    }
+}
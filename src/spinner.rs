@@ -1,13 +1,18 @@
 use std::time::Duration;
 
-use indicatif::{ProgressBar, ProgressStyle};
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
 
-use crate::assets::DOTS;
+use crate::{assets::DOTS, report::is_redirected};
 
 /// Displays a spinner and provides a closure to be called when done.
 pub(crate) fn display_spinner() -> impl Fn() {
     let spinner = ProgressBar::new_spinner();
 
+    // Nothing is drawn over the dashboard.
+    if is_redirected() {
+        spinner.set_draw_target(ProgressDrawTarget::hidden());
+    }
+
     spinner.enable_steady_tick(Duration::from_millis(50));
     spinner.set_style(
         ProgressStyle::with_template("{spinner:.magenta} {msg}")
@@ -18,3 +23,21 @@ pub(crate) fn display_spinner() -> impl Fn() {
 
     move || spinner.finish_and_clear()
 }
+
+/// Displays a progress bar over the given number of items, the message being
+/// the item currently processed.
+pub(crate) fn display_progress_bar(length: u64) -> ProgressBar {
+    let progress_bar = ProgressBar::new(length);
+
+    progress_bar.enable_steady_tick(Duration::from_millis(50));
+    progress_bar.set_style(
+        ProgressStyle::with_template(
+            "{spinner:.magenta} [{bar:30.magenta}] {pos}/{len} {wide_msg}",
+        )
+        .unwrap()
+        .tick_strings(DOTS)
+        .progress_chars("=> "),
+    );
+
+    progress_bar
+}
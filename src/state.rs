@@ -0,0 +1,340 @@
+use std::{
+    mem,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use image::RgbaImage;
+
+use crate::{
+    args::Args,
+    data_uri::{get_payload_hash, parse_encoded_image},
+    planner::SizeSpec,
+};
+
+/// State shared between the stream and the controls.
+pub type SharedState = Arc<Mutex<State>>;
+
+/// Processing state which can be changed without restarting the process.
+/// Note: The fields are kept private and exposed by the implementations.
+#[derive(Debug)]
+pub struct State {
+    args: Args,
+    collected: Vec<RgbaImage>,
+    flags: Vec<String>,
+    is_paused: bool,
+    is_stitch_requested: bool,
+    keep_originals: bool,
+    original: Option<RgbaImage>,
+    previous_hash: Option<u64>,
+    processed: usize,
+    saved_paths: Vec<PathBuf>,
+    size_flags: Option<Vec<String>>,
+}
+
+impl State {
+    /// Creates the state from the arguments and the flags they were parsed
+    /// from.
+    /// The original images are only kept when they can be restored.
+    #[must_use]
+    pub fn new(args: Args, flags: Vec<String>, keep_originals: bool) -> Self {
+        Self {
+            args,
+            collected: Vec::new(),
+            flags,
+            is_paused: false,
+            is_stitch_requested: false,
+            keep_originals,
+            original: None,
+            previous_hash: None,
+            processed: 0,
+            saved_paths: Vec::new(),
+            size_flags: None,
+        }
+    }
+
+    /// Returns the arguments used for the next images.
+    #[must_use]
+    pub fn get_args(&self) -> &Args {
+        &self.args
+    }
+
+    /// Replaces the arguments used for the next images.
+    pub fn set_args(&mut self, args: Args, flags: Vec<String>) {
+        self.args = args;
+        self.flags = flags;
+        self.size_flags = None;
+    }
+
+    /// Replaces the size flags used for the next images with the size
+    /// specification picked with the settings wizard.
+    pub(crate) fn set_size_spec(&mut self, size_spec: SizeSpec) {
+        self.size_flags = Some(self.args.set_size_spec(size_spec));
+    }
+
+    /// Checks if the processing is paused.
+    #[must_use]
+    pub fn is_paused(&self) -> bool {
+        self.is_paused
+    }
+
+    /// Pauses or resumes the processing.
+    pub fn set_paused(&mut self, is_paused: bool) {
+        self.is_paused = is_paused;
+    }
+
+    /// Checks if the image with the given hash hasn't been seen yet.
+    pub(crate) fn is_new_image(&self, hash: u64) -> bool {
+        self.previous_hash != Some(hash)
+    }
+
+    /// Keeps track of the last image seen, or moved to the clipboard.
+    pub(crate) fn set_previous_hash(&mut self, hash: u64) {
+        self.previous_hash = Some(hash);
+    }
+
+    /// Keeps track of the snippet moved to the clipboard, so that an emitted
+    /// data URI isn't processed again.
+    pub fn set_emitted_snippet(&mut self, snippet: &str) {
+        if let Some(payload) = parse_encoded_image(snippet) {
+            self.previous_hash = Some(get_payload_hash(payload));
+        }
+    }
+
+    /// Checks if the original images are kept to be restored.
+    #[must_use]
+    pub fn keeps_originals(&self) -> bool {
+        self.keep_originals
+    }
+
+    /// Keeps track of a processed image, and of its original if needed.
+    pub(crate) fn add_processed(&mut self, original: Option<RgbaImage>) {
+        self.processed += 1;
+
+        if self.keep_originals {
+            self.original = original;
+        }
+    }
+
+    /// Checks if the original of the last processed image can be restored.
+    #[must_use]
+    pub fn has_original(&self) -> bool {
+        self.original.is_some()
+    }
+
+    /// Takes the original of the last processed image, to restore it.
+    pub fn take_original(&mut self) -> Option<RgbaImage> {
+        self.original.take()
+    }
+
+    /// Keeps track of the files saved by the process, so that they aren't
+    /// picked from the watched directory.
+    pub fn add_saved_paths(&mut self, paths: &[PathBuf]) {
+        self.saved_paths.extend_from_slice(paths);
+    }
+
+    /// Takes the files saved since the last call.
+    pub(crate) fn take_saved_paths(&mut self) -> Vec<PathBuf> {
+        mem::take(&mut self.saved_paths)
+    }
+
+    /// Collects an image to be stitched later on, returning the number of
+    /// collected images.
+    pub(crate) fn collect(&mut self, image_buffer: RgbaImage) -> usize {
+        self.collected.push(image_buffer);
+
+        self.collected.len()
+    }
+
+    /// Requests the collected images to be stitched, returning their number.
+    pub fn request_stitch(&mut self) -> usize {
+        self.is_stitch_requested = true;
+
+        self.collected.len()
+    }
+
+    /// Takes the collected images once the count is reached, or once
+    /// requested.
+    pub(crate) fn take_collected(&mut self) -> Option<Vec<RgbaImage>> {
+        let count = self.args.collect.unwrap_or_default() as usize;
+        let is_ready = mem::take(&mut self.is_stitch_requested)
+            || (count > 0 && self.collected.len() >= count);
+
+        (is_ready && !self.collected.is_empty()).then(|| mem::take(&mut self.collected))
+    }
+
+    /// Returns a one line summary of the state.
+    #[must_use]
+    pub fn get_status(&self) -> String {
+        let mut flags = if self.flags.is_empty() {
+            String::from("none")
+        } else {
+            self.flags.join(" ")
+        };
+
+        if let Some(size_flags) = &self.size_flags {
+            flags = format!("{flags}, size set to {}", size_flags.join(" "));
+        }
+
+        let collected = if self.collected.is_empty() {
+            String::new()
+        } else {
+            format!(", {} image(s) collected", self.collected.len())
+        };
+
+        format!(
+            "{}, {} image(s) processed{}, flags: {}.",
+            if self.is_paused { "Paused" } else { "Running" },
+            self.processed,
+            collected,
+            flags
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use image::RgbaImage;
+
+    use super::State;
+    use crate::{
+        args::Args,
+        data_uri::{encode_data_uri, get_payload_hash, parse_encoded_image},
+        hidpi::HiDpiScale,
+        planner::SizeSpec,
+    };
+
+    fn get_state(keep_originals: bool) -> State {
+        let flags = vec![String::from("--width-percent"), String::from("50")];
+        let args = Args::parse_from(["picst", "--width-percent", "50"]);
+
+        State::new(args, flags, keep_originals)
+    }
+
+    #[test]
+    fn check_status() {
+        let mut state = get_state(false);
+        assert_eq!(
+            state.get_status(),
+            "Running, 0 image(s) processed, flags: --width-percent 50."
+        );
+
+        state.set_paused(true);
+        state.add_processed(None);
+        state.set_args(Args::parse_from(["picst"]), Vec::new());
+        assert_eq!(
+            state.get_status(),
+            "Paused, 1 image(s) processed, flags: none."
+        );
+    }
+
+    #[test]
+    fn check_size_spec() {
+        let mut state = get_state(false);
+
+        // The size flags are replaced by the picked size specification.
+        state.set_size_spec(SizeSpec::Pixels(None, Some(800)));
+        assert_eq!(state.get_args().width_percent, None);
+        assert_eq!(state.get_args().width, Some(800));
+        assert_eq!(
+            state.get_status(),
+            "Running, 0 image(s) processed, flags: --width-percent 50, size set to --width 800."
+        );
+
+        state.set_size_spec(SizeSpec::HiDpi(2));
+        assert_eq!(state.get_args().width, None);
+        assert_eq!(state.get_args().hidpi, Some(HiDpiScale::Factor(2)));
+
+        // The density of the physical lengths is kept for the saved files.
+        state.set_size_spec(SizeSpec::Physical(None, Some(591), 300));
+        assert_eq!(state.get_args().width, Some(591));
+        assert_eq!(state.get_args().get_dpi(), 300);
+        assert!(state
+            .get_status()
+            .ends_with("size set to --width 591 --dpi 300."));
+
+        // The boxes are described by the size expression.
+        state.set_size_spec(SizeSpec::Fill(600, 800));
+        assert_eq!(state.get_args().hidpi, None);
+        assert_eq!(state.get_args().size, Some(SizeSpec::Fill(600, 800)));
+        assert!(state.get_status().ends_with("size set to --size 800x600^."));
+
+        // Until the flags are replaced.
+        state.set_args(Args::parse_from(["picst"]), Vec::new());
+        assert_eq!(
+            state.get_status(),
+            "Running, 0 image(s) processed, flags: none."
+        );
+    }
+
+    #[test]
+    fn check_originals() {
+        let mut state = get_state(false);
+        state.add_processed(Some(RgbaImage::new(1, 1)));
+        assert!(state.take_original().is_none());
+
+        let mut state = get_state(true);
+        state.add_processed(Some(RgbaImage::new(1, 1)));
+        assert!(state.take_original().is_some());
+        assert!(state.take_original().is_none());
+    }
+
+    #[test]
+    fn check_is_new_image() {
+        let mut state = get_state(false);
+        assert!(state.is_new_image(1));
+
+        state.set_previous_hash(1);
+        assert!(!state.is_new_image(1));
+        assert!(state.is_new_image(2));
+    }
+
+    #[test]
+    fn check_emitted_snippet() {
+        let mut state = get_state(false);
+        let data_uri = encode_data_uri(&RgbaImage::new(1, 1), 96).unwrap();
+        let hash = get_payload_hash(parse_encoded_image(&data_uri).unwrap());
+
+        // Only the data URIs could be processed again.
+        state.set_emitted_snippet(&format!("![]({data_uri})"));
+        assert!(state.is_new_image(hash));
+
+        state.set_emitted_snippet(&data_uri);
+        assert!(!state.is_new_image(hash));
+    }
+
+    #[test]
+    fn check_take_collected() {
+        let mut state = get_state(false);
+        state.set_args(Args::parse_from(["picst", "--collect", "2"]), Vec::new());
+
+        // The count has to be reached.
+        assert_eq!(state.collect(RgbaImage::new(1, 1)), 1);
+        assert!(state.take_collected().is_none());
+        assert_eq!(state.collect(RgbaImage::new(1, 1)), 2);
+        assert_eq!(
+            state.take_collected().map(|collected| collected.len()),
+            Some(2)
+        );
+        assert!(state.take_collected().is_none());
+
+        // Unless the stitch is requested.
+        state.collect(RgbaImage::new(1, 1));
+        assert_eq!(
+            state.get_status(),
+            "Running, 0 image(s) processed, 1 image(s) collected, flags: none."
+        );
+        assert_eq!(state.request_stitch(), 1);
+        assert_eq!(
+            state.take_collected().map(|collected| collected.len()),
+            Some(1)
+        );
+
+        // Requests without any image are dropped.
+        state.request_stitch();
+        assert!(state.take_collected().is_none());
+        state.collect(RgbaImage::new(1, 1));
+        assert!(state.take_collected().is_none());
+    }
+}
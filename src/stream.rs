@@ -1,83 +1,685 @@
-use std::io::Read;
+use std::{path::PathBuf, slice};
 
 use anyhow::Result;
-use arboard::Clipboard;
-use async_stream::try_stream;
+use arboard::{Clipboard, ImageData};
+use async_stream::stream;
 use futures::Stream;
-use image::{imageops, RgbaImage};
-use itertools::equal;
-use tokio::time::{sleep, Duration, Instant};
+use image::RgbaImage;
+use tokio::{
+    select,
+    task::{spawn_blocking, JoinHandle},
+    time::{sleep, Duration, Instant},
+};
 
 use crate::{
-    args::Args, resized_image::ResizedImage, spinner::display_spinner, wizard::create_wizard,
+    args::Args,
+    assets::COLLECT,
+    collect::stitch,
+    data_uri::{decode_image, get_payload_hash, parse_encoded_image},
+    file_list::{get_file_list_hash, load_image, parse_file_list},
+    filter::apply_filters,
+    image_data::{get_hash, get_sampled_hash, into_image_buffer},
+    preview::{Confirmation, Protocol},
+    report::{report, Level},
+    resize::ResizeOptions,
+    resized_image::ResizedImage,
+    skip::Skip,
+    spinner::display_spinner,
+    state::SharedState,
+    variants::{ResizedBuffers, Targets},
+    watch::DirWatcher,
+    wizard::{confirm_preview, prompt_settings},
 };
 
 static SLEEP_TIME_MS: u64 = 250;
 
-/// Main loop stream polling on the clipboard content.
-pub(crate) fn get_stream(args: Args) -> impl Stream<Item = Result<ResizedImage>> {
-    try_stream! {
+/// Content of the clipboard to process.
+enum Input {
+    /// Image data.
+    Image(RgbaImage),
+    /// Image files copied from a file manager.
+    Files(Vec<PathBuf>),
+    /// Text of an image copied as a data URI or as base64 text, its payload
+    /// being extracted again once known to be new.
+    Encoded(String),
+}
+
+/// Content of the clipboard, read from the system clipboard outside of the
+/// tests.
+trait ClipboardContent {
+    /// Returns the image data of the clipboard, if any.
+    fn read_image(&mut self) -> Option<ImageData<'static>>;
+
+    /// Returns the text of the clipboard, if any.
+    fn read_text(&mut self) -> Option<String>;
+}
+
+impl ClipboardContent for Clipboard {
+    fn read_image(&mut self) -> Option<ImageData<'static>> {
+        self.get_image().ok()
+    }
+
+    fn read_text(&mut self) -> Option<String> {
+        self.get_text().ok()
+    }
+}
+
+/// Reads the clipboard content to process, along with its hash.
+/// Note: the copied files and the encoded images are only decoded once known
+/// to be new.
+fn read_clipboard(clipboard: &mut impl ClipboardContent) -> Option<(u64, Input)> {
+    // Take ownership of the clipboard image bytes.
+    if let Some(image_buffer) = clipboard.read_image().and_then(into_image_buffer) {
+        return Some((get_hash(&image_buffer), Input::Image(image_buffer)));
+    }
+
+    read_clipboard_text(clipboard)
+}
+
+/// Reads the copied files or the encoded image of the clipboard text, along
+/// with their hash.
+fn read_clipboard_text(clipboard: &mut impl ClipboardContent) -> Option<(u64, Input)> {
+    let text = clipboard.read_text()?;
+
+    // File managers copy the files as a list of URIs or paths.
+    if let Some(paths) = parse_file_list(&text) {
+        return Some((get_file_list_hash(&paths), Input::Files(paths)));
+    }
+
+    // Images can also be copied as data URIs or base64 text, the payload
+    // being hashed in place and the text moved as is.
+    let hash = get_payload_hash(parse_encoded_image(&text)?);
+
+    Some((hash, Input::Encoded(text)))
+}
+
+/// Returns whether a new image, new files or a new encoded image have been
+/// copied since the given hash of the content being processed.
+/// The sampled hash is updated to the one of the image currently in the
+/// clipboard, if any.
+/// Note: the image is only entirely hashed once its sampled hash changes, to
+/// keep polling cheap on large images, while the text is hashed as is.
+fn has_new_clipboard_content(
+    clipboard: &mut impl ClipboardContent,
+    state: &SharedState,
+    hash: u64,
+    sampled_hash: &mut u64,
+) -> bool {
+    let current_hash = match clipboard.read_image().and_then(into_image_buffer) {
+        Some(image_buffer) => {
+            let current_sampled_hash = get_sampled_hash(&image_buffer);
+
+            if current_sampled_hash == *sampled_hash {
+                return false;
+            }
+
+            *sampled_hash = current_sampled_hash;
+
+            get_hash(&image_buffer)
+        }
+        None => match read_clipboard_text(clipboard) {
+            Some((current_hash, _)) => current_hash,
+            None => return false,
+        },
+    };
+
+    current_hash != hash && state.lock().unwrap().is_new_image(current_hash)
+}
+
+/// Waits for the resize job while polling on the clipboard content.
+/// Returns `None` if a newer image has been copied in the meantime, the job
+/// being abandoned.
+/// Note: the blocking task can't be interrupted, an abandoned job runs to
+/// completion and its result is dropped.
+async fn wait_for_job<T>(
+    mut job: JoinHandle<T>,
+    mut is_stale: impl FnMut() -> bool,
+) -> Result<Option<T>> {
+    loop {
+        select! {
+            resized_buffer = &mut job => {
+                // A newer image might have been copied right before the end
+                // of the job.
+                return Ok(if is_stale() { None } else { Some(resized_buffer?) });
+            }
+            () = sleep(Duration::from_millis(SLEEP_TIME_MS)) => {
+                if is_stale() {
+                    return Ok(None);
+                }
+            }
+        }
+    }
+}
+
+/// Loads the images of the clipboard content, along with the path of their
+/// original file if any.
+/// Note: the images not matching the criteria of the arguments are skipped.
+fn load_images(input: Input, args: &Args) -> Vec<(Option<PathBuf>, Result<RgbaImage>)> {
+    let images = match input {
+        Input::Image(image_buffer) => vec![(None, Ok(image_buffer))],
+        Input::Files(paths) => paths
+            .into_iter()
+            .map(|path| {
+                let image_buffer = load_image(&path);
+
+                (Some(path), image_buffer)
+            })
+            .collect(),
+        Input::Encoded(text) => vec![(
+            None,
+            decode_image(parse_encoded_image(&text).unwrap_or_default()),
+        )],
+    };
+
+    images
+        .into_iter()
+        .map(|(source_path, maybe_image_buffer)| {
+            (
+                source_path,
+                maybe_image_buffer.and_then(|image_buffer| apply_filters(args, image_buffer)),
+            )
+        })
+        .collect()
+}
+
+/// Outcome of the processing of an image.
+enum Outcome {
+    /// The image has been resized.
+    Resized(ResizedImage),
+    /// The image has been skipped, the reason being reported.
+    Skipped,
+    /// A newer image has been copied in the meantime.
+    Stale,
+}
+
+/// Previews the resized image and asks for a confirmation, the dimensions
+/// being re-edited from the original image as many times as requested.
+/// Returns `None` if cancelled.
+async fn confirm_resized_buffers(
+    args: &Args,
+    protocol: Protocol,
+    original: &RgbaImage,
+    mut resized_buffers: ResizedBuffers,
+) -> Result<Option<ResizedBuffers>> {
+    loop {
+        match confirm_preview(protocol, &resized_buffers.image_buffer)? {
+            Confirmation::Accept => return Ok(Some(resized_buffers)),
+            Confirmation::Edit => {
+                let size_spec = prompt_settings(args)?;
+                let targets =
+                    Targets::from_size_spec(&size_spec, (original.height(), original.width()));
+                let image_buffer = original.clone();
+                let options = ResizeOptions::from(args);
+
+                let on_done = display_spinner();
+                resized_buffers =
+                    spawn_blocking(move || targets.resize(image_buffer, &options)).await?;
+                on_done();
+            }
+            Confirmation::Cancel => return Ok(None),
+        }
+    }
+}
+
+/// Processes an image, from the wizard to the resize operation, while polling
+/// on the clipboard content.
+async fn process_image(
+    clipboard: &mut impl ClipboardContent,
+    state: &SharedState,
+    args: &Args,
+    hash: u64,
+    source_path: Option<PathBuf>,
+    maybe_image_buffer: Result<RgbaImage>,
+) -> Result<Outcome> {
+    // Create a wizard to handle all the necessary user prompts, for each
+    // variant if any.
+    let maybe_targets = maybe_image_buffer.and_then(|image_buffer| {
+        let targets = Targets::new(args, &image_buffer)?;
+
+        Ok((image_buffer, targets))
+    });
+    let (image_buffer, targets) = match maybe_targets {
+        Ok(image_buffer_and_targets) => image_buffer_and_targets,
+        Err(error) => {
+            // Skipped images are reported, the other errors are propagated.
+            let skip = error.downcast::<Skip>()?;
+
+            report(Level::Warning, skip);
+
+            return Ok(Outcome::Skipped);
+        }
+    };
+
+    // Keep track of the start time of the resize operation.
+    let start_time = Instant::now();
+
+    // Display a spinner and get a closure to end it.
+    let on_done = display_spinner();
+
+    // Keep track of the original dimensions.
+    let (original_width, original_height) = image_buffer.dimensions();
+
+    // The original image is handed back by the resize job if it can be
+    // restored, or to re-edit the dimensions once previewed if the user can
+    // be prompted, instead of being copied.
+    let preview = args.preview.filter(|_| args.is_interactive());
+    let keep_original = preview.is_some() || state.lock().unwrap().keeps_originals();
+
+    // Proceed with the image resizing operation on the blocking pool, to keep
+    // polling on the clipboard.
+    // Anything else than new content, e.g. the previous resized image, is not
+    // a newer image, whether copied as image data, as files or as encoded
+    // text, and the clipboard is only polled if it's not ignored.
+    let options = ResizeOptions::from(args);
+    let mut sampled_hash = get_sampled_hash(&image_buffer);
+    let job = spawn_blocking(move || {
+        if keep_original {
+            let resized_buffers = targets.resize_borrowed(&image_buffer, &options);
+
+            (resized_buffers, Some(image_buffer))
+        } else {
+            (targets.resize(image_buffer, &options), None)
+        }
+    });
+    let maybe_resized_buffers = wait_for_job(job, || {
+        !args.watch_only && has_new_clipboard_content(clipboard, state, hash, &mut sampled_hash)
+    })
+    .await?;
+
+    // Stop the spinner.
+    on_done();
+
+    let Some((resized_buffers, original)) = maybe_resized_buffers else {
+        report(
+            Level::Info,
+            "A newer image has been copied, skipping this one.",
+        );
+
+        return Ok(Outcome::Stale);
+    };
+
+    // The cancelled images are left untouched in the clipboard, and the time
+    // spent confirming isn't part of the duration.
+    let (resized_buffers, start_time) = match (preview, &original) {
+        (Some(protocol), Some(original)) => {
+            let elapsed = start_time.elapsed();
+            let Some(resized_buffers) =
+                confirm_resized_buffers(args, protocol, original, resized_buffers).await?
+            else {
+                report(Level::Info, "Cancelled, the clipboard is left untouched.");
+
+                return Ok(Outcome::Skipped);
+            };
+
+            (resized_buffers, Instant::now() - elapsed)
+        }
+        _ => (resized_buffers, start_time),
+    };
+
+    let ResizedBuffers {
+        descriptor,
+        image_buffer: resized_buffer,
+        variants,
+        dpi,
+    } = resized_buffers;
+
+    // Keep track of the resized image which is going to be moved to the
+    // clipboard.
+    {
+        let mut state = state.lock().unwrap();
+
+        state.set_previous_hash(get_hash(&resized_buffer));
+        state.add_processed(original);
+    }
+
+    Ok(Outcome::Resized(
+        ResizedImage::new(
+            resized_buffer,
+            original_height,
+            original_width,
+            source_path,
+            start_time,
+        )
+        .with_variants(descriptor, variants)
+        .with_dpi(dpi),
+    ))
+}
+
+/// Reports the copied files left unprocessed once a newer image has been
+/// copied, if any.
+fn report_remaining_files(images: impl Iterator<Item = (Option<PathBuf>, Result<RgbaImage>)>) {
+    let paths = images
+        .filter_map(|(source_path, _)| source_path)
+        .map(|path| path.display().to_string())
+        .collect::<Vec<_>>();
+
+    if !paths.is_empty() {
+        report(
+            Level::Warning,
+            format!(
+                "The remaining copied file(s) are skipped too: {}.",
+                paths.join(", ")
+            ),
+        );
+    }
+}
+
+/// Gathers the content to process, along with the path of the watched file it
+/// comes from, if any, and its hash.
+fn read_inputs(
+    clipboard: &mut impl ClipboardContent,
+    watcher: Option<&mut DirWatcher>,
+    state: &SharedState,
+    watch_only: bool,
+) -> Vec<(Option<PathBuf>, u64, Input)> {
+    let mut inputs = Vec::new();
+
+    if !watch_only {
+        if let Some((hash, input)) = read_clipboard(clipboard) {
+            inputs.push((None, hash, input));
+        }
+    }
+
+    if let Some(watcher) = watcher {
+        // The files saved in the meantime aren't new images.
+        for path in state.lock().unwrap().take_saved_paths() {
+            watcher.ignore(&path);
+        }
+
+        if let Some(path) = watcher.next_file() {
+            let hash = get_file_list_hash(slice::from_ref(&path));
+
+            inputs.push((Some(path.clone()), hash, Input::Files(vec![path])));
+        }
+    }
+
+    inputs
+}
+
+/// Main loop stream polling on the clipboard content, and on the watched
+/// directory if any.
+/// The state is read for each new image, so that it can be changed by the
+/// controls in the meantime.
+/// The errors are yielded without ending the stream, the content they come
+/// from being flagged as seen.
+///
+/// # Panics
+///
+/// Panics if the clipboard is not available or if the state is poisoned.
+pub fn get_stream(state: SharedState) -> impl Stream<Item = Result<ResizedImage>> {
+    stream! {
         // Get a new instance of the clipboard.
         let mut clipboard = Clipboard::new().unwrap();
 
-        // Keep track of the previous images for comparison.
-        let mut previous_image: Option<Vec<u8>>= None;
-
-        loop {
-            if let Ok(image) = clipboard.get_image() {
-                // Use a bool to flag for noop.
-                let mut skip_iteration = false;
-
-                // Convert the clipboard image to an image buffer.
-                let image_buffer = RgbaImage::from_raw(image.width as u32, image.height as u32, image.bytes.to_vec()).unwrap();
-
-                // Try to get the bytes from the new image buffer.
-                let maybe_bytes: Result<Vec<u8>, _> = image_buffer.bytes().collect();
-
-                if let Ok(bytes) = maybe_bytes {
-                    // Try to unwrap the previous image.
-                    if let Some(ref buffer) = previous_image {
-                        // If we have a mismatch, we assume that we have
-                        // a new image from the clipboard.
-                        // Store it.
-                        if equal(&bytes, buffer) {
-                            skip_iteration = true;
-                        } else {
-                            previous_image = Some(bytes);
-                        }
+        // Get a watcher of the directory, if any.
+        let mut watcher: Option<DirWatcher> = None;
+
+        'polling: loop {
+            let (watch_dir, watch_only) = {
+                let state = state.lock().unwrap();
+
+                (state.get_args().watch_dir.clone(), state.get_args().watch_only)
+            };
+
+            // Follow the changes of the watched directory.
+            if watcher.as_ref().map(DirWatcher::get_directory) != watch_dir.as_deref() {
+                watcher = watch_dir.as_deref().map(DirWatcher::new);
+            }
+
+            let inputs = read_inputs(&mut clipboard, watcher.as_mut(), &state, watch_only);
+
+            for (watched_path, hash, input) in inputs {
+                // Only the clipboard content is tracked by its hash, the
+                // watched files being only returned once.
+                let is_clipboard = watched_path.is_none();
+
+                // If we have a mismatch, we assume that we have a new image
+                // from the clipboard.
+                let maybe_args = {
+                    let mut state = state.lock().unwrap();
+
+                    // Images copied while paused are ignored, even once
+                    // resumed.
+                    if state.is_paused() && is_clipboard {
+                        state.set_previous_hash(hash);
                     }
-                }
 
-                if !skip_iteration {
-                    // Create a wizard to handle all the necessary user prompts.
-                    let (height, width) = create_wizard(&args, &image)?;
+                    let is_new_image = if is_clipboard {
+                        state.is_new_image(hash)
+                    } else {
+                        !state.is_paused()
+                    };
+
+                    is_new_image.then(|| state.get_args().clone())
+                };
+
+                if let Some(args) = maybe_args {
+                    let images = load_images(input, &args);
 
-                    // Keep track of the start time of the resize operation.
-                    let start_time = Instant::now();
+                    // Collected images are kept as is, and flagged as seen.
+                    if let Some(count) = args.collect {
+                        if is_clipboard {
+                            state.lock().unwrap().set_previous_hash(hash);
+                        }
+
+                        for (_, maybe_image_buffer) in images {
+                            match maybe_image_buffer {
+                                Ok(image_buffer) => {
+                                    let collected = state.lock().unwrap().collect(image_buffer);
+
+                                    print_collected(collected, count);
+                                }
+                                Err(error) => match error.downcast::<Skip>() {
+                                    Ok(skip) => report(Level::Warning, skip),
+                                    Err(error) => yield Err(error),
+                                },
+                            }
+                        }
+                    } else {
+                        let mut is_processed = false;
 
-                    // Display a spinner and get a closure to end it.
-                    let on_done = display_spinner();
+                        let mut images = images.into_iter();
 
-                    // Proceed with the image resizing operation.
-                    let resized_buffer = imageops::resize(&image_buffer, width, height, imageops::FilterType::Lanczos3);
+                        while let Some((source_path, maybe_image_buffer)) = images.next() {
+                            match process_image(&mut clipboard, &state, &args, hash, source_path, maybe_image_buffer).await {
+                                Ok(Outcome::Resized(resized_image)) => {
+                                    is_processed = true;
 
-                    // Try to get the bytes from the new image buffer.
-                    let maybe_resized_bytes: Result<Vec<u8>, _> = resized_buffer.bytes().collect();
+                                    yield Ok(resized_image);
+                                }
+                                Ok(Outcome::Skipped) => {}
+                                Err(error) => yield Err(error),
+                                // Process the newer image right away, the
+                                // watched file being processed again later,
+                                // and the other copied files being reported.
+                                Ok(Outcome::Stale) => {
+                                    report_remaining_files(images);
 
-                    if let Ok(bytes) = maybe_resized_bytes {
-                        // Keep track of the resized image which is going
-                        // to be move to the clipboard.
-                        previous_image = Some(bytes);
+                                    if let (Some(watcher), Some(path)) = (&mut watcher, watched_path) {
+                                        watcher.retry(path);
+                                    }
 
-                        // Stop the spinner.
-                        on_done();
+                                    continue 'polling;
+                                }
+                            }
+                        }
 
-                        yield ResizedImage::new(resized_buffer, image_buffer.height(),image_buffer.width(), start_time)
+                        // Skipped content is flagged as seen.
+                        if !is_processed && is_clipboard {
+                            state.lock().unwrap().set_previous_hash(hash);
+                        }
                     }
                 }
+            }
+
+            // Stitch the collected images once the count is reached, or once
+            // requested.
+            let maybe_collected = {
+                let mut state = state.lock().unwrap();
+                let args = state.get_args().clone();
+
+                state.take_collected().map(|collected| (collected, args))
             };
 
+            if let Some((collected, args)) = maybe_collected {
+                // The collected images are dropped if they can't be stitched.
+                match stitch(&collected, args.layout, args.spacing, args.background) {
+                    Ok(stitched) => {
+                        let hash = get_hash(&stitched);
+
+                        match process_image(&mut clipboard, &state, &args, hash, None, Ok(stitched)).await {
+                            Ok(Outcome::Resized(resized_image)) => yield Ok(resized_image),
+                            Ok(_) => {}
+                            Err(error) => yield Err(error),
+                        }
+                    }
+                    Err(error) => report(Level::Error, error),
+                }
+            }
+
             sleep(Duration::from_millis(SLEEP_TIME_MS)).await;
         }
     }
 }
+
+/// Prints the number of collected images, out of the count to reach if any.
+fn print_collected(collected: usize, count: u32) {
+    let progress = if count == 0 {
+        format!("{collected} image(s) collected, press Enter to stitch them.")
+    } else {
+        format!("{collected} of {count} image(s) collected.")
+    };
+
+    report(Level::Info, format!("{COLLECT}{progress}"));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        borrow::Cow,
+        sync::{Arc, Mutex},
+        thread,
+        time::Duration,
+    };
+
+    use arboard::ImageData;
+    use clap::Parser;
+    use image::RgbaImage;
+    use tokio::task::{spawn_blocking, JoinHandle};
+
+    use super::{
+        load_images, process_image, read_clipboard, wait_for_job, ClipboardContent, Outcome,
+    };
+    use crate::{
+        args::Args,
+        counting::{get_tracked_allocations, track_allocations_of},
+        image_data::as_image_data,
+        state::State,
+    };
+
+    /// Clipboard holding an image until it's read, the later reads finding
+    /// nothing new.
+    struct TestClipboard(Option<ImageData<'static>>);
+
+    impl ClipboardContent for TestClipboard {
+        fn read_image(&mut self) -> Option<ImageData<'static>> {
+            self.0.take()
+        }
+
+        fn read_text(&mut self) -> Option<String> {
+            None
+        }
+    }
+
+    /// Returns a resize job taking a while to complete.
+    fn get_slow_job() -> JoinHandle<RgbaImage> {
+        spawn_blocking(|| {
+            thread::sleep(Duration::from_millis(600));
+
+            RgbaImage::new(1, 1)
+        })
+    }
+
+    #[tokio::test]
+    async fn check_processing_copies_image_at_most_once() {
+        // The size is unique to this test, so that only the copies of its
+        // image are counted.
+        let (width, height) = (967, 541);
+        let image_size = width * height * 4;
+
+        // Only a flattened image that can be restored needs a copy.
+        for (flags, keep_originals, copies) in [
+            ("--width 400", false, 0),
+            ("--width 400", true, 0),
+            ("--width 400 --flatten #ffffff", false, 0),
+            ("--width 400 --flatten #ffffff", true, 1),
+        ] {
+            let mut clipboard = TestClipboard(Some(ImageData {
+                bytes: Cow::Owned(vec![127; image_size]),
+                height,
+                width,
+            }));
+            let args = Args::parse_from(format!("picst {flags}").split_whitespace());
+            let state = Arc::new(Mutex::new(State::new(
+                args.clone(),
+                Vec::new(),
+                keep_originals,
+            )));
+
+            track_allocations_of(image_size);
+
+            let (hash, input) = read_clipboard(&mut clipboard).unwrap();
+            let (source_path, maybe_image_buffer) = load_images(input, &args).pop().unwrap();
+            let outcome = process_image(
+                &mut clipboard,
+                &state,
+                &args,
+                hash,
+                source_path,
+                maybe_image_buffer,
+            )
+            .await
+            .unwrap();
+            let Outcome::Resized(resized_image) = outcome else {
+                panic!("{flags}: the image hasn't been resized");
+            };
+
+            // The resized image is borrowed by the clipboard.
+            let image_data = as_image_data(resized_image.get_buffer());
+
+            assert_eq!(image_data.width, 400, "{flags}");
+            assert_eq!(get_tracked_allocations(), copies, "{flags}");
+            assert_eq!(state.lock().unwrap().has_original(), keep_originals);
+        }
+    }
+
+    #[tokio::test]
+    async fn check_wait_for_job_completes() {
+        let resized_buffer = wait_for_job(get_slow_job(), || false).await.unwrap();
+        assert!(resized_buffer.is_some());
+    }
+
+    #[tokio::test]
+    async fn check_wait_for_job_abandons_stale_job() {
+        let mut polls = 0;
+
+        let resized_buffer = wait_for_job(get_slow_job(), || {
+            polls += 1;
+
+            polls > 1
+        })
+        .await
+        .unwrap();
+
+        assert!(resized_buffer.is_none());
+    }
+
+    #[tokio::test]
+    async fn check_wait_for_job_discards_outdated_result() {
+        let job = spawn_blocking(|| RgbaImage::new(1, 1));
+
+        let resized_buffer = wait_for_job(job, || true).await.unwrap();
+
+        assert!(resized_buffer.is_none());
+    }
+}
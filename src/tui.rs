@@ -0,0 +1,567 @@
+use std::{
+    env,
+    io::{self, Stdout},
+    sync::mpsc::channel,
+    time::Duration,
+};
+
+use anyhow::Result;
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use futures::{pin_mut, Stream, StreamExt};
+use indicatif::{BinaryBytes, HumanDuration};
+use ratatui::{
+    backend::{Backend, CrosstermBackend},
+    layout::{Constraint, Direction, Layout},
+    style::{Color, Modifier, Style},
+    widgets::{Block, Borders, Cell, List, ListItem, ListState, Paragraph, Row, Table, TableState},
+    Frame, Terminal,
+};
+use tokio::{select, time::sleep};
+
+use crate::{
+    args::{Args, ArgsResult},
+    planner::SizeSpec,
+    report::{redirect, report, Level},
+    resized_image::ResizedImage,
+    state::SharedState,
+    variants::Variants,
+    wizard::DEFAULT_FLAGS_CONFIG,
+};
+
+/// Delay between two redraws of the dashboard, the keys being polled in
+/// between.
+static TICK_MS: u64 = 100;
+
+/// Maximum number of messages kept in the dashboard.
+static MAX_MESSAGES: usize = 100;
+
+/// Widths of the columns of the history.
+static HISTORY_WIDTHS: &[Constraint] = &[
+    Constraint::Percentage(25),
+    Constraint::Length(11),
+    Constraint::Length(11),
+    Constraint::Length(10),
+    Constraint::Length(10),
+    Constraint::Min(10),
+];
+
+/// Presets of the settings pane, replacing the size flags once applied.
+static PRESETS: &[(&str, SizeSpec)] = &[
+    ("25% of the width", SizeSpec::Percent(None, Some(25))),
+    ("50% of the width", SizeSpec::Percent(None, Some(50))),
+    ("75% of the width", SizeSpec::Percent(None, Some(75))),
+    ("640px wide", SizeSpec::Pixels(None, Some(640))),
+    ("1280px wide", SizeSpec::Pixels(None, Some(1280))),
+    ("1920px wide", SizeSpec::Pixels(None, Some(1920))),
+    ("High DPI, 2x", SizeSpec::HiDpi(2)),
+    ("2 megapixels", SizeSpec::PixelCount(2_000_000)),
+];
+
+/// Checks if the images are skipped for lack of size flags, nothing being
+/// prompted from the dashboard, unless there are default flags.
+fn needs_preset(args: &Args, has_default_flags: bool) -> bool {
+    ArgsResult::get(args) == ArgsResult::NoFlags
+        && Variants::from_args(args).is_none()
+        && !has_default_flags
+}
+
+/// Returns the status line, and whether it needs the attention of the user,
+/// i.e. if the processing is paused or if the images are skipped until a
+/// preset is applied.
+fn get_status(state: &SharedState) -> (String, bool) {
+    let state = state.lock().unwrap();
+    let status = state.get_status();
+
+    if needs_preset(
+        state.get_args(),
+        env::var_os(DEFAULT_FLAGS_CONFIG).is_some(),
+    ) {
+        return (
+            format!("{status} Select a preset (Tab, Enter) to resize the images."),
+            true,
+        );
+    }
+
+    (status, state.is_paused())
+}
+
+/// Keys of the dashboard, shown at the bottom.
+static KEYS_HELP: &str =
+    " q: quit, p: pause/resume, tab: switch pane, ↑/↓: scroll or select, enter: apply preset";
+
+/// Pane receiving the arrow keys.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Pane {
+    History,
+    Settings,
+}
+
+/// Processed image shown in the history.
+#[derive(Debug)]
+struct Entry {
+    /// Name of the original file, or `clipboard`.
+    source: String,
+    /// Original dimensions, as a tuple of (width, height).
+    original: (u32, u32),
+    /// Resized dimensions, as a tuple of (width, height).
+    resized: (u32, u32),
+    /// Byte size of the resized image.
+    bytes: u64,
+    /// Duration of the processing.
+    duration: Duration,
+    /// Where the resized image went, i.e. the last saved file or the
+    /// clipboard.
+    output: String,
+}
+
+impl From<&ResizedImage> for Entry {
+    fn from(resized_image: &ResizedImage) -> Self {
+        let source = resized_image
+            .get_source_path()
+            .and_then(|path| path.file_name())
+            .map_or_else(
+                || String::from("clipboard"),
+                |name| name.to_string_lossy().into_owned(),
+            );
+        let output = match (
+            resized_image.get_saved_paths().last(),
+            resized_image.get_emitted(),
+        ) {
+            (Some(path), _) => path.display().to_string(),
+            (None, Some(emit)) => format!("clipboard, {}", emit.get_description()),
+            (None, None) => String::from("clipboard"),
+        };
+
+        Self {
+            source,
+            original: resized_image.get_original_dimensions(),
+            resized: resized_image.get_buffer().dimensions(),
+            bytes: resized_image.get_buffer().as_raw().len() as u64,
+            duration: resized_image.get_elapsed(),
+            output,
+        }
+    }
+}
+
+/// State of the dashboard, besides the shared one.
+#[derive(Debug)]
+struct Dashboard {
+    focus: Pane,
+    history: Vec<Entry>,
+    history_state: TableState,
+    messages: Vec<(Level, String)>,
+    presets_state: ListState,
+}
+
+impl Dashboard {
+    fn new() -> Self {
+        let mut presets_state = ListState::default();
+        presets_state.select(Some(0));
+
+        Self {
+            focus: Pane::History,
+            history: Vec::new(),
+            history_state: TableState::default(),
+            messages: Vec::new(),
+            presets_state,
+        }
+    }
+
+    /// Adds a processed image to the history, following the latest one
+    /// unless scrolled up.
+    fn push_entry(&mut self, entry: Entry) {
+        let is_following = self
+            .history_state
+            .selected()
+            .map_or(true, |selected| selected + 1 == self.history.len());
+
+        self.history.push(entry);
+
+        if is_following {
+            self.history_state.select(Some(self.history.len() - 1));
+        }
+    }
+
+    /// Adds a message, dropping the oldest ones.
+    fn push_message(&mut self, level: Level, message: String) {
+        self.messages.push((level, message));
+
+        if self.messages.len() > MAX_MESSAGES {
+            self.messages.remove(0);
+        }
+    }
+
+    /// Moves the selection of the focused pane up or down.
+    fn move_selection(&mut self, is_down: bool) {
+        let (selected, length) = match self.focus {
+            Pane::History => (self.history_state.selected(), self.history.len()),
+            Pane::Settings => (self.presets_state.selected(), PRESETS.len()),
+        };
+
+        if length == 0 {
+            return;
+        }
+
+        let selected = match selected {
+            Some(selected) if is_down => (selected + 1).min(length - 1),
+            Some(selected) => selected.saturating_sub(1),
+            None => length - 1,
+        };
+
+        match self.focus {
+            Pane::History => self.history_state.select(Some(selected)),
+            Pane::Settings => self.presets_state.select(Some(selected)),
+        }
+    }
+
+    /// Applies the key, returning `false` to quit.
+    fn handle_key(&mut self, key: KeyEvent, state: &SharedState) -> bool {
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return false,
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Char('p') => {
+                let mut state = state.lock().unwrap();
+                let is_paused = !state.is_paused();
+
+                state.set_paused(is_paused);
+                drop(state);
+
+                report(Level::Info, if is_paused { "Paused." } else { "Resumed." });
+            }
+            KeyCode::Tab => {
+                self.focus = match self.focus {
+                    Pane::History => Pane::Settings,
+                    Pane::Settings => Pane::History,
+                };
+            }
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(false),
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(true),
+            KeyCode::Enter if self.focus == Pane::Settings => {
+                if let Some((label, size_spec)) = self
+                    .presets_state
+                    .selected()
+                    .and_then(|selected| PRESETS.get(selected))
+                {
+                    state.lock().unwrap().set_size_spec(*size_spec);
+
+                    report(Level::Info, format!("Preset applied: {label}."));
+                }
+            }
+            _ => {}
+        }
+
+        true
+    }
+
+    /// Draws the status, the history, the presets, the messages and the
+    /// keys.
+    fn draw<B: Backend>(&mut self, frame: &mut Frame<B>, state: &SharedState) {
+        let (status, needs_attention) = get_status(state);
+
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Min(5),
+                Constraint::Length(8),
+                Constraint::Length(1),
+            ])
+            .split(frame.size());
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(70), Constraint::Percentage(30)])
+            .split(rows[1]);
+
+        let get_block = |title: &'static str, pane: Option<Pane>| {
+            let block = Block::default().borders(Borders::ALL).title(title);
+
+            if pane.is_some() && pane == Some(self.focus) {
+                block.border_style(Style::default().fg(Color::Magenta))
+            } else {
+                block
+            }
+        };
+        let highlight_style = Style::default()
+            .fg(Color::Magenta)
+            .add_modifier(Modifier::BOLD);
+
+        let status_style = if needs_attention {
+            Style::default().fg(Color::Yellow)
+        } else {
+            Style::default()
+        };
+        frame.render_widget(
+            Paragraph::new(status)
+                .style(status_style)
+                .block(get_block(" picst ", None)),
+            rows[0],
+        );
+
+        let history = self.history.iter().map(|entry| {
+            let print_dimensions = |(width, height): (u32, u32)| format!("{width}x{height}");
+
+            Row::new(vec![
+                Cell::from(entry.source.clone()),
+                Cell::from(print_dimensions(entry.original)),
+                Cell::from(print_dimensions(entry.resized)),
+                Cell::from(BinaryBytes(entry.bytes).to_string()),
+                Cell::from(HumanDuration(entry.duration).to_string()),
+                Cell::from(entry.output.clone()),
+            ])
+        });
+        let header = Row::new(vec![
+            "Source", "Original", "Resized", "Bytes", "Duration", "Output",
+        ])
+        .style(Style::default().add_modifier(Modifier::BOLD));
+        frame.render_stateful_widget(
+            Table::new(history)
+                .header(header)
+                .widths(HISTORY_WIDTHS)
+                .highlight_style(highlight_style)
+                .block(get_block(" History ", Some(Pane::History))),
+            columns[0],
+            &mut self.history_state,
+        );
+
+        let presets = PRESETS
+            .iter()
+            .map(|(label, _)| ListItem::new(*label))
+            .collect::<Vec<_>>();
+        frame.render_stateful_widget(
+            List::new(presets)
+                .highlight_style(highlight_style)
+                .highlight_symbol("> ")
+                .block(get_block(" Presets ", Some(Pane::Settings))),
+            columns[1],
+            &mut self.presets_state,
+        );
+
+        // Only the latest messages fitting in the pane are shown.
+        let visible = usize::from(rows[2].height.saturating_sub(2));
+        let messages = self
+            .messages
+            .iter()
+            .skip(self.messages.len().saturating_sub(visible))
+            .map(|(level, message)| {
+                let style = match level {
+                    Level::Info => Style::default().fg(Color::Gray),
+                    Level::Warning => Style::default().fg(Color::Yellow),
+                    Level::Error => Style::default().fg(Color::Red),
+                };
+
+                ListItem::new(message.as_str()).style(style)
+            })
+            .collect::<Vec<_>>();
+        frame.render_widget(
+            List::new(messages).block(get_block(" Messages ", None)),
+            rows[2],
+        );
+
+        frame.render_widget(
+            Paragraph::new(KEYS_HELP).style(Style::default().fg(Color::DarkGray)),
+            rows[3],
+        );
+    }
+}
+
+/// Switches the terminal to the alternate screen, reading the keys as they
+/// are pressed.
+fn setup_terminal() -> Result<Terminal<CrosstermBackend<Stdout>>> {
+    enable_raw_mode()?;
+
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+
+    Ok(Terminal::new(CrosstermBackend::new(stdout))?)
+}
+
+/// Restores the terminal as it was before the dashboard.
+fn restore_terminal(terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> Result<()> {
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    terminal.show_cursor()?;
+
+    Ok(())
+}
+
+/// Shows the dashboard until quit, consuming the stream and handing each
+/// resized image to the closure, which returns whether it has been moved to
+/// the clipboard.
+/// The reported messages are shown in the dashboard meanwhile, along with
+/// the errors of the stream and of the closure, the dashboard staying open.
+///
+/// # Errors
+///
+/// Fails if the terminal can't be drawn on.
+pub async fn run<S>(
+    state: SharedState,
+    stream: S,
+    mut on_resized: impl FnMut(&mut ResizedImage) -> Result<bool>,
+) -> Result<()>
+where
+    S: Stream<Item = Result<ResizedImage>>,
+{
+    let (sender, receiver) = channel();
+    let mut terminal = setup_terminal()?;
+    let mut dashboard = Dashboard::new();
+
+    redirect(Some(sender));
+    pin_mut!(stream);
+
+    let result = async {
+        loop {
+            for (level, message) in receiver.try_iter() {
+                dashboard.push_message(level, message);
+            }
+
+            terminal.draw(|frame| dashboard.draw(frame, &state))?;
+
+            while event::poll(Duration::ZERO)? {
+                if let Event::Key(key) = event::read()? {
+                    if key.kind == KeyEventKind::Press && !dashboard.handle_key(key, &state) {
+                        return Ok(());
+                    }
+                }
+            }
+
+            select! {
+                maybe_resized_image = stream.next() => {
+                    let Some(maybe_resized_image) = maybe_resized_image else {
+                        return Ok(());
+                    };
+
+                    // The errors are shown without closing the dashboard, the
+                    // stream going on with the next images.
+                    let maybe_moved = maybe_resized_image.and_then(|mut resized_image| {
+                        let is_moved = on_resized(&mut resized_image)?;
+
+                        Ok((resized_image, is_moved))
+                    });
+
+                    match maybe_moved {
+                        Ok((resized_image, true)) => {
+                            dashboard.push_entry(Entry::from(&resized_image));
+                        }
+                        Ok((_, false)) => {
+                            report(Level::Error, "Moving the image to the clipboard failed!");
+                        }
+                        Err(error) => report(Level::Error, error),
+                    }
+                }
+                () = sleep(Duration::from_millis(TICK_MS)) => {}
+            }
+        }
+    }
+    .await;
+
+    redirect(None);
+    restore_terminal(&mut terminal)?;
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        path::PathBuf,
+        sync::{Arc, Mutex},
+    };
+
+    use clap::Parser;
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use image::RgbaImage;
+    use ratatui::{backend::TestBackend, Terminal};
+    use tokio::time::Instant;
+
+    use super::{needs_preset, Dashboard, Entry, Pane};
+    use crate::{args::Args, report::Level, resized_image::ResizedImage, state::State};
+
+    fn get_key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn check_entry_from_resized_image() {
+        let mut resized_image = ResizedImage::new(
+            RgbaImage::new(4, 2),
+            4,
+            8,
+            Some(PathBuf::from("/tmp/shot.png")),
+            Instant::now(),
+        );
+
+        let entry = Entry::from(&resized_image);
+        assert_eq!(entry.source, "shot.png");
+        assert_eq!((entry.original, entry.resized), ((8, 4), (4, 2)));
+        assert_eq!(entry.bytes, 4 * 2 * 4);
+        assert_eq!(entry.output, "clipboard");
+
+        resized_image.set_saved_path(&PathBuf::from("/tmp/shot-picst.png"));
+        assert_eq!(Entry::from(&resized_image).output, "/tmp/shot-picst.png");
+    }
+
+    #[test]
+    fn check_dashboard() {
+        let state = Arc::new(Mutex::new(State::new(
+            Args::parse_from(["picst", "--width", "800"]),
+            vec![String::from("--width"), String::from("800")],
+            false,
+        )));
+        let mut dashboard = Dashboard::new();
+
+        // The presets replace the size flags.
+        assert!(dashboard.handle_key(get_key(KeyCode::Tab), &state));
+        assert_eq!(dashboard.focus, Pane::Settings);
+        assert!(dashboard.handle_key(get_key(KeyCode::Down), &state));
+        assert!(dashboard.handle_key(get_key(KeyCode::Enter), &state));
+        assert_eq!(state.lock().unwrap().get_args().width, None);
+        assert_eq!(state.lock().unwrap().get_args().width_percent, Some(50));
+
+        assert!(dashboard.handle_key(get_key(KeyCode::Char('p')), &state));
+        assert!(state.lock().unwrap().is_paused());
+
+        dashboard.push_entry(Entry::from(&ResizedImage::new(
+            RgbaImage::new(640, 360),
+            1080,
+            1920,
+            None,
+            Instant::now(),
+        )));
+        dashboard.push_message(Level::Warning, String::from("Image skipped."));
+
+        let mut terminal = Terminal::new(TestBackend::new(120, 30)).unwrap();
+        terminal
+            .draw(|frame| dashboard.draw(frame, &state))
+            .unwrap();
+        let content = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol.as_str())
+            .collect::<String>();
+
+        assert!(content.contains("Paused, 0 image(s) processed"));
+        assert!(content.contains("1920x1080"));
+        assert!(content.contains("640x360"));
+        assert!(content.contains("Image skipped."));
+
+        assert!(!dashboard.handle_key(get_key(KeyCode::Char('q')), &state));
+    }
+
+    #[test]
+    fn check_needs_preset() {
+        let get_args = |flags: &str| Args::parse_from(format!("picst {flags}").split_whitespace());
+
+        // Nothing is prompted, the images being skipped without size flags.
+        assert!(needs_preset(&get_args(""), false));
+        assert!(needs_preset(&get_args("--flatten #ffffff"), false));
+        assert!(!needs_preset(&get_args(""), true));
+        assert!(!needs_preset(&get_args("--width 800"), false));
+        assert!(!needs_preset(&get_args("--srcset-widths 320,640"), false));
+    }
+}
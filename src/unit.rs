@@ -3,7 +3,7 @@ use std::convert::TryFrom;
 use anyhow::Result;
 
 /// Units used for the select prompt.
-static UNITS: &[&str; 3] = &["Pixel", "Percentage", "Ratio"];
+static UNITS: &[&str; 5] = &["Pixel", "Percentage", "Ratio", "Physical", "HiDPI"];
 
 /// Enumeration for the unit.
 #[derive(Debug, PartialEq)]
@@ -11,11 +11,13 @@ pub(crate) enum Unit {
     Pixel = 0,
     Percentage = 1,
     Ratio = 2,
+    Physical = 3,
+    HiDpi = 4,
 }
 
 impl Unit {
     /// Returns the static units.
-    pub(crate) fn get_items() -> &'static [&'static str; 3] {
+    pub(crate) fn get_items() -> &'static [&'static str; 5] {
         UNITS
     }
 }
@@ -28,6 +30,8 @@ impl TryFrom<usize> for Unit {
             u if u == Unit::Pixel as usize => Ok(Unit::Pixel),
             u if u == Unit::Percentage as usize => Ok(Unit::Percentage),
             u if u == Unit::Ratio as usize => Ok(Unit::Ratio),
+            u if u == Unit::Physical as usize => Ok(Unit::Physical),
+            u if u == Unit::HiDpi as usize => Ok(Unit::HiDpi),
             // Unreachable.
             _ => Err("Index cannot be converted to unit."),
         }
@@ -51,5 +55,11 @@ mod tests {
 
         let two_to_unit: Unit = 2usize.try_into().unwrap();
         assert_eq!(two_to_unit, Unit::Ratio);
+
+        let three_to_unit: Unit = 3usize.try_into().unwrap();
+        assert_eq!(three_to_unit, Unit::Physical);
+
+        let four_to_unit: Unit = 4usize.try_into().unwrap();
+        assert_eq!(four_to_unit, Unit::HiDpi);
     }
 }
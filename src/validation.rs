@@ -1,3 +1,13 @@
+use std::path::PathBuf;
+
+use image::Rgb;
+
+use crate::{
+    hidpi::HiDpiScale,
+    physical::{PhysicalLength, PhysicalUnit},
+    planner::SizeSpec,
+};
+
 ///  Validator for percent.
 ///  Tries to parse as string slice to a `u32`.
 pub(crate) fn percent_validator(s: &str) -> Result<u32, String> {
@@ -47,9 +57,223 @@ pub(crate) fn ratio_validator(s: &str) -> Result<f32, String> {
     }
 }
 
+///  Validator for megapixels.
+///  Tries to parse as string slice to a `f32`.
+pub(crate) fn megapixels_validator(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(parsed) => {
+            // Below one pixel, the target can't be reached.
+            if parsed * 1_000_000. < 1. || !parsed.is_finite() {
+                Err(String::from(
+                    "Megapixels must be a number greater than or equal to 0.000001.",
+                ))
+            } else {
+                Ok(parsed)
+            }
+        }
+        Err(_) => Err(format!("`{s}` can't be parsed as megapixels.")),
+    }
+}
+
+///  Validator for physical lengths.
+///  Tries to parse as string slice to a `PhysicalLength`, e.g. `10cm`.
+pub(crate) fn physical_length_validator(s: &str) -> Result<PhysicalLength, String> {
+    let trimmed = s.trim();
+    let split_at = trimmed.len().saturating_sub(2);
+
+    match (
+        trimmed.get(..split_at).map(str::parse::<f32>),
+        trimmed.get(split_at..).and_then(PhysicalUnit::from_suffix),
+    ) {
+        (Some(Ok(parsed)), Some(unit)) => {
+            if parsed <= 0. || !parsed.is_finite() {
+                Err(String::from("Length must be a positive number."))
+            } else {
+                Ok(PhysicalLength::new(parsed, unit))
+            }
+        }
+        _ => Err(format!(
+            "`{s}` can't be parsed as a length in `cm`, `mm` or `in`."
+        )),
+    }
+}
+
+///  Validator for dots per inch.
+///  Tries to parse as string slice to a `u16`.
+pub(crate) fn dpi_validator(s: &str) -> Result<u16, String> {
+    match s.parse::<u16>() {
+        Ok(parsed) => {
+            if parsed == 0 {
+                Err(String::from("DPI must be a non-zero integer."))
+            } else {
+                Ok(parsed)
+            }
+        }
+        Err(_) => Err(format!("`{s}` can't be parsed as DPI.")),
+    }
+}
+
+///  Validator for pixel densities.
+///  Tries to parse as string slice to a `u32`, with an optional `x` suffix.
+pub(crate) fn density_validator(s: &str) -> Result<u32, String> {
+    match s.strip_suffix('x').unwrap_or(s).parse::<u32>() {
+        Ok(parsed) => {
+            if parsed == 0 {
+                Err(String::from("Density must be a non-zero integer."))
+            } else {
+                Ok(parsed)
+            }
+        }
+        Err(_) => Err(format!("`{s}` can't be parsed as a density.")),
+    }
+}
+
+///  Validator for the high DPI scale.
+///  Tries to parse as string slice to a `HiDpiScale`, either `auto` or an
+///  integer factor.
+pub(crate) fn hidpi_validator(s: &str) -> Result<HiDpiScale, String> {
+    if s == "auto" {
+        return Ok(HiDpiScale::Auto);
+    }
+
+    match s.parse::<u32>() {
+        Ok(parsed) => {
+            if parsed < 2 {
+                Err(String::from(
+                    "Scale must be an integer greater than or equal to two.",
+                ))
+            } else {
+                Ok(HiDpiScale::Factor(parsed))
+            }
+        }
+        Err(_) => Err(format!(
+            "`{s}` can't be parsed as a scale, use `auto`, `2` or `3`."
+        )),
+    }
+}
+
+///  Validator for directories.
+///  Checks that the string slice is the path of an existing directory.
+pub(crate) fn directory_validator(s: &str) -> Result<PathBuf, String> {
+    let path = PathBuf::from(s);
+
+    if path.is_dir() {
+        Ok(path)
+    } else {
+        Err(format!("`{s}` is not a directory."))
+    }
+}
+
+///  Validator for colors.
+///  Tries to parse as string slice to a `Rgb<u8>`, from a hexadecimal
+///  notation with an optional leading `#`, e.g. `#ffffff` or `fff`.
+pub(crate) fn color_validator(s: &str) -> Result<Rgb<u8>, String> {
+    let hex = s.strip_prefix('#').unwrap_or(s);
+    let error = || format!("`{s}` can't be parsed as a hexadecimal color.");
+
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(error());
+    }
+
+    // Expand the shorthand notation, e.g. `fff` to `ffffff`.
+    let hex = match hex.len() {
+        3 => hex.chars().flat_map(|c| [c, c]).collect(),
+        6 => hex.to_owned(),
+        _ => return Err(error()),
+    };
+
+    let channel =
+        |index: usize| u8::from_str_radix(&hex[index..index + 2], 16).map_err(|_| error());
+
+    Ok(Rgb([channel(0)?, channel(2)?, channel(4)?]))
+}
+
+///  Validator for size expressions, in the style of the `ImageMagick` geometry.
+///  Tries to parse as string slice to a `SizeSpec`, either a width in pixels
+///  (`800`, `800px` or `800x`), a height (`x300`), a box to fit in
+///  (`800x600`), to fill (`800x600^`) or to match exactly (`800x600!`),
+///  percentages (`50%` or `50x25%`) or a ratio (`0.5x` or `2/3`).
+///  Note: like `ImageMagick`, a whole number followed by `x` is a width, the
+///  ratios needing a decimal point, e.g. `2.0x`.
+pub(crate) fn size_validator(s: &str) -> Result<SizeSpec, String> {
+    let trimmed = s.trim().to_lowercase();
+    let error = || {
+        format!(
+            "`{s}` can't be parsed as a size, e.g. `800`, `x300`, `800x600!`, `50%`, `0.5x` or `2/3`."
+        )
+    };
+
+    // Fractions and ratios scale both dimensions.
+    if let Some((numerator, denominator)) = trimmed.split_once('/') {
+        let parse = |value: &str| value.trim().parse::<f32>().map_err(|_| error());
+        let ratio = parse(numerator)? / parse(denominator)?;
+
+        if !ratio.is_finite() {
+            return Err(error());
+        }
+
+        return ratio_validator(&ratio.to_string()).map(SizeSpec::Ratio);
+    }
+
+    // Whole numbers are widths with a missing height.
+    if let Some(ratio) = trimmed
+        .strip_suffix('x')
+        .filter(|ratio| !trimmed.ends_with("px") && ratio.parse::<u32>().is_err())
+    {
+        return ratio_validator(ratio).map(SizeSpec::Ratio);
+    }
+
+    // The trailing flag applies to the whole expression.
+    let (geometry, flag) = match trimmed.char_indices().last() {
+        Some((index, flag @ ('!' | '^' | '%'))) => (&trimmed[..index], Some(flag)),
+        _ => (trimmed.strip_suffix("px").unwrap_or(&trimmed), None),
+    };
+    let (width, height) = match geometry.split_once('x') {
+        Some((width, height)) => (
+            Some(width).filter(|width| !width.is_empty()),
+            Some(height).filter(|height| !height.is_empty()),
+        ),
+        None => (Some(geometry), None),
+    };
+    let parse = |value: Option<&str>| {
+        value
+            .map(|value| {
+                if flag == Some('%') {
+                    percent_validator(value).map_err(|_| error())
+                } else {
+                    pixels_validator(value).map_err(|_| error())
+                }
+            })
+            .transpose()
+    };
+    let (height, width) = (parse(height)?, parse(width)?);
+
+    match (flag, height, width) {
+        (_, None, None) => Err(error()),
+        (Some('%'), height, width) => Ok(SizeSpec::Percent(height, width)),
+        (Some('!'), Some(height), Some(width)) => Ok(SizeSpec::Pixels(Some(height), Some(width))),
+        (Some('^'), Some(height), Some(width)) => Ok(SizeSpec::Fill(height, width)),
+        (None, Some(height), Some(width)) => Ok(SizeSpec::Fit(height, width)),
+        (None, height, width) => Ok(SizeSpec::Pixels(height, width)),
+        // Both dimensions are needed to ignore the aspect ratio or to fill.
+        _ => Err(error()),
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{percent_validator, pixels_validator, ratio_validator};
+    use image::Rgb;
+
+    use super::{
+        color_validator, density_validator, directory_validator, dpi_validator, hidpi_validator,
+        megapixels_validator, percent_validator, physical_length_validator, pixels_validator,
+        ratio_validator, size_validator,
+    };
+    use crate::{
+        hidpi::HiDpiScale,
+        physical::{PhysicalLength, PhysicalUnit},
+        planner::SizeSpec,
+    };
 
     #[test]
     fn check_percent_validator() {
@@ -73,4 +297,125 @@ mod tests {
         assert!(ratio_validator("0.").is_err());
         assert!(ratio_validator("0.7").is_ok());
     }
+
+    #[test]
+    fn check_megapixels_validator() {
+        assert!(megapixels_validator("nope").is_err());
+        assert!(megapixels_validator("0").is_err());
+        assert!(megapixels_validator("-2").is_err());
+        assert!(megapixels_validator("inf").is_err());
+        assert!(megapixels_validator("0.5").is_ok());
+        assert!(megapixels_validator("2").is_ok());
+    }
+
+    #[test]
+    fn check_physical_length_validator() {
+        assert!(physical_length_validator("nope").is_err());
+        assert!(physical_length_validator("10").is_err());
+        assert!(physical_length_validator("10px").is_err());
+        assert!(physical_length_validator("0cm").is_err());
+        assert!(physical_length_validator("-1in").is_err());
+        assert_eq!(
+            physical_length_validator("12.5cm"),
+            Ok(PhysicalLength::new(12.5, PhysicalUnit::Centimeter))
+        );
+        assert_eq!(
+            physical_length_validator("4in"),
+            Ok(PhysicalLength::new(4., PhysicalUnit::Inch))
+        );
+    }
+
+    #[test]
+    fn check_dpi_validator() {
+        assert!(dpi_validator("nope").is_err());
+        assert!(dpi_validator("0").is_err());
+        assert!(dpi_validator("70000").is_err());
+        assert!(dpi_validator("300").is_ok());
+    }
+
+    #[test]
+    fn check_density_validator() {
+        assert!(density_validator("nope").is_err());
+        assert!(density_validator("0x").is_err());
+        assert!(density_validator("1.5x").is_err());
+        assert_eq!(density_validator("2"), Ok(2));
+        assert_eq!(density_validator("3x"), Ok(3));
+    }
+
+    #[test]
+    fn check_hidpi_validator() {
+        assert!(hidpi_validator("nope").is_err());
+        assert!(hidpi_validator("1").is_err());
+        assert!(hidpi_validator("1.5").is_err());
+        assert_eq!(hidpi_validator("auto"), Ok(HiDpiScale::Auto));
+        assert_eq!(hidpi_validator("2"), Ok(HiDpiScale::Factor(2)));
+        assert_eq!(hidpi_validator("3"), Ok(HiDpiScale::Factor(3)));
+    }
+
+    #[test]
+    fn check_color_validator() {
+        assert!(color_validator("nope").is_err());
+        assert!(color_validator("#ff").is_err());
+        assert!(color_validator("#gggggg").is_err());
+        assert_eq!(color_validator("#ffffff"), Ok(Rgb([255, 255, 255])));
+        assert_eq!(color_validator("0a0B0c"), Ok(Rgb([10, 11, 12])));
+        assert_eq!(color_validator("#f80"), Ok(Rgb([255, 136, 0])));
+    }
+
+    #[test]
+    fn check_directory_validator() {
+        assert!(directory_validator("src").is_ok());
+        assert!(directory_validator("src/lib.rs").is_err());
+        assert!(directory_validator("nope").is_err());
+    }
+
+    #[test]
+    fn check_size_validator() {
+        assert_eq!(size_validator("800"), Ok(SizeSpec::Pixels(None, Some(800))));
+        assert_eq!(
+            size_validator("800px"),
+            Ok(SizeSpec::Pixels(None, Some(800)))
+        );
+        assert_eq!(
+            size_validator("x300"),
+            Ok(SizeSpec::Pixels(Some(300), None))
+        );
+        assert_eq!(
+            size_validator("800x"),
+            Ok(SizeSpec::Pixels(None, Some(800)))
+        );
+        assert_eq!(size_validator("2x"), Ok(SizeSpec::Pixels(None, Some(2))));
+        assert_eq!(size_validator("800x600"), Ok(SizeSpec::Fit(600, 800)));
+        assert_eq!(
+            size_validator("800X600!"),
+            Ok(SizeSpec::Pixels(Some(600), Some(800)))
+        );
+        assert_eq!(size_validator("800x600^"), Ok(SizeSpec::Fill(600, 800)));
+        assert_eq!(size_validator("50%"), Ok(SizeSpec::Percent(None, Some(50))));
+        assert_eq!(
+            size_validator("50x25%"),
+            Ok(SizeSpec::Percent(Some(25), Some(50)))
+        );
+        assert_eq!(size_validator("0.5x"), Ok(SizeSpec::Ratio(0.5)));
+        assert_eq!(size_validator("2.0x"), Ok(SizeSpec::Ratio(2.)));
+        assert_eq!(size_validator(" 3/4 "), Ok(SizeSpec::Ratio(0.75)));
+
+        for size in [
+            "",
+            "nope",
+            "0",
+            "x",
+            "0x",
+            "800x!",
+            "800!",
+            "x300^",
+            "100%",
+            "1.0x",
+            "2/0",
+            "2/2",
+            "800x600%!",
+        ] {
+            assert!(size_validator(size).is_err(), "{size}");
+        }
+    }
 }
@@ -0,0 +1,366 @@
+use std::{
+    iter::once,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use image::RgbaImage;
+
+use crate::{
+    args::Args,
+    encoder::save,
+    image_data::as_image_data,
+    planner::{plan, DimensionTuple, ResizeTarget, SizeSpec},
+    resize::{resize_borrowed_images, resize_images, ResizeOptions},
+    resized_image::ResizedImage,
+    snippet::get_srcset_snippet,
+    wizard::create_wizard,
+};
+
+/// Target of a variant along with its descriptor, e.g. `640w` or `2x`.
+pub type VariantTarget = (String, ResizeTarget);
+
+/// Responsive variants of the resized image, for the `srcset` attribute.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Variants {
+    /// Widths in pixels, e.g. `640w`, the aspect ratio being preserved.
+    Widths(Vec<u32>),
+    /// Pixel densities, e.g. `2x`, the size specification giving the `1x`
+    /// dimensions.
+    Densities(Vec<u32>),
+}
+
+impl Variants {
+    /// Returns the variants requested by the arguments, if any.
+    #[must_use]
+    pub fn from_args(args: &Args) -> Option<Self> {
+        match (&args.srcset_widths, &args.srcset_densities) {
+            (Some(widths), _) if !widths.is_empty() => Some(Variants::Widths(widths.clone())),
+            (_, Some(densities)) if !densities.is_empty() => {
+                Some(Variants::Densities(densities.clone()))
+            }
+            _ => None,
+        }
+    }
+
+    /// Plans the target of each variant from the original dimensions and the
+    /// `1x` ones, used by the densities, as tuples of (height, width).
+    /// The variants are sorted from the smallest to the largest one.
+    #[must_use]
+    pub fn plan(&self, original: DimensionTuple, base: DimensionTuple) -> Vec<VariantTarget> {
+        let (values, suffix) = match self {
+            Variants::Widths(widths) => (widths, 'w'),
+            Variants::Densities(densities) => (densities, 'x'),
+        };
+
+        let mut values = values.clone();
+        values.sort_unstable();
+        values.dedup();
+
+        values
+            .into_iter()
+            .map(|value| {
+                let size_spec = match self {
+                    Variants::Widths(_) => SizeSpec::Pixels(None, Some(value)),
+                    Variants::Densities(_) => SizeSpec::Pixels(
+                        Some(base.0.saturating_mul(value)),
+                        Some(base.1.saturating_mul(value)),
+                    ),
+                };
+
+                (format!("{value}{suffix}"), plan(&size_spec, original))
+            })
+            .collect()
+    }
+}
+
+/// Targets of the resize operation of an image.
+#[derive(Debug)]
+pub(crate) struct Targets {
+    /// Descriptor of the main target, if there are variants.
+    descriptor: Option<String>,
+    /// Main target, i.e. the largest variant if there are some.
+    target: ResizeTarget,
+    /// Other variants, from the smallest to the largest one.
+    variants: Vec<VariantTarget>,
+    /// Dots per inch picked along with the size specification, if any.
+    dpi: Option<u16>,
+}
+
+impl Targets {
+    /// Returns the targets of the resize operation, prompting for the size
+    /// specification only if it's needed.
+    pub(crate) fn new(args: &Args, image_buffer: &RgbaImage) -> Result<Self> {
+        let original = (image_buffer.height(), image_buffer.width());
+        let Some(variants) = Variants::from_args(args) else {
+            return Ok(Self::from_size_spec(
+                &create_wizard(args, &as_image_data(image_buffer))?,
+                original,
+            ));
+        };
+
+        // Only the densities are relative to the size specification.
+        let (base, dpi) = match variants {
+            Variants::Widths(_) => (original, None),
+            Variants::Densities(_) => {
+                let size_spec = create_wizard(args, &as_image_data(image_buffer))?;
+
+                (
+                    plan(&size_spec, original).get_dimensions(original),
+                    size_spec.get_dpi(),
+                )
+            }
+        };
+
+        let mut variants = variants.plan(original, base);
+        let (descriptor, target) = variants
+            .pop()
+            .unwrap_or_else(|| (String::from("1x"), ResizeTarget::Dimensions(base)));
+
+        Ok(Self {
+            descriptor: Some(descriptor),
+            target,
+            variants,
+            dpi,
+        })
+    }
+
+    /// Returns the target of the size specification, from the original
+    /// dimensions, without variants.
+    pub(crate) fn from_size_spec(size_spec: &SizeSpec, original: DimensionTuple) -> Self {
+        Self {
+            dpi: size_spec.get_dpi(),
+            ..Self::from(plan(size_spec, original))
+        }
+    }
+
+    /// Returns all the targets, the main one being the last one.
+    fn get_targets(&self) -> Vec<&ResizeTarget> {
+        self.variants
+            .iter()
+            .map(|(_, target)| target)
+            .chain(once(&self.target))
+            .collect()
+    }
+
+    /// Resizes the image buffer to all the targets, borrowing it for each of
+    /// them.
+    pub(crate) fn resize(self, image_buffer: RgbaImage, options: &ResizeOptions) -> ResizedBuffers {
+        let resized_buffers = resize_images(image_buffer, &self.get_targets(), options);
+
+        self.into_resized_buffers(resized_buffers)
+    }
+
+    /// Resizes the borrowed image buffer to all the targets, so that it can
+    /// be kept as is, e.g. as the original to restore.
+    pub(crate) fn resize_borrowed(
+        self,
+        image_buffer: &RgbaImage,
+        options: &ResizeOptions,
+    ) -> ResizedBuffers {
+        let resized_buffers = resize_borrowed_images(image_buffer, &self.get_targets(), options);
+
+        self.into_resized_buffers(resized_buffers)
+    }
+
+    /// Matches the resized image buffers, in the order of the targets, with
+    /// the descriptors.
+    fn into_resized_buffers(self, mut resized_buffers: Vec<RgbaImage>) -> ResizedBuffers {
+        let image_buffer = resized_buffers.pop().unwrap_or_default();
+        let variants = self
+            .variants
+            .into_iter()
+            .map(|(descriptor, _)| descriptor)
+            .zip(resized_buffers)
+            .collect();
+
+        ResizedBuffers {
+            descriptor: self.descriptor,
+            image_buffer,
+            variants,
+            dpi: self.dpi,
+        }
+    }
+}
+
+impl From<ResizeTarget> for Targets {
+    fn from(target: ResizeTarget) -> Self {
+        Self {
+            descriptor: None,
+            target,
+            variants: Vec::new(),
+            dpi: None,
+        }
+    }
+}
+
+/// Image buffers resized to the targets.
+#[derive(Debug)]
+pub(crate) struct ResizedBuffers {
+    /// Descriptor of the main image buffer, if there are variants.
+    pub(crate) descriptor: Option<String>,
+    /// Main image buffer, i.e. the largest variant if there are some.
+    pub(crate) image_buffer: RgbaImage,
+    /// Other variants, from the smallest to the largest one.
+    pub(crate) variants: Vec<(String, RgbaImage)>,
+    /// Dots per inch picked along with the size specification, if any.
+    pub(crate) dpi: Option<u16>,
+}
+
+/// Returns the path of a variant, e.g. `shot-640w.png` for `shot.png`.
+#[must_use]
+pub fn get_variant_path(path: &Path, descriptor: &str) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy())
+        .unwrap_or_default();
+    let extension = path.extension().map_or_else(
+        || String::from("png"),
+        |extension| extension.to_string_lossy().into_owned(),
+    );
+
+    path.with_file_name(format!("{stem}-{descriptor}.{extension}"))
+}
+
+/// Saves the variants of the resized image next to the given path, e.g.
+/// `shot-640w.png` for `shot.png`, and returns the HTML image element
+/// referencing them in its `srcset` attribute.
+///
+/// # Errors
+///
+/// Fails if any variant can't be saved.
+pub fn save_variants(resized_image: &mut ResizedImage, path: &Path, dpi: u16) -> Result<String> {
+    let mut sources = Vec::new();
+
+    for (descriptor, image_buffer) in resized_image.get_variants() {
+        let variant_path = get_variant_path(path, descriptor);
+
+        save(image_buffer, &variant_path, dpi)?;
+
+        sources.push((
+            descriptor.to_owned(),
+            variant_path,
+            image_buffer.dimensions(),
+        ));
+    }
+
+    for (_, variant_path, _) in &sources {
+        resized_image.set_saved_path(variant_path);
+    }
+
+    Ok(get_srcset_snippet(&sources))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env, fs,
+        path::{Path, PathBuf},
+        process,
+    };
+
+    use clap::Parser;
+    use image::RgbaImage;
+    use tokio::time::Instant;
+
+    use super::{get_variant_path, Targets, Variants};
+    use crate::{
+        args::Args,
+        encoder::save,
+        physical::DEFAULT_DPI,
+        planner::{ResizeTarget, SizeSpec},
+        resize::ResizeOptions,
+        resized_image::ResizedImage,
+    };
+
+    #[test]
+    fn check_variants_from_args() {
+        let get_variants =
+            |flags: &str| Args::try_parse_from(format!("picst {flags}").split_whitespace());
+
+        assert_eq!(
+            Variants::from_args(&get_variants("--srcset-widths 320,640").unwrap()),
+            Some(Variants::Widths(vec![320, 640]))
+        );
+        assert_eq!(
+            Variants::from_args(&get_variants("--width 800 --srcset-densities 1x,2x").unwrap()),
+            Some(Variants::Densities(vec![1, 2]))
+        );
+        assert_eq!(Variants::from_args(&get_variants("").unwrap()), None);
+
+        // The widths replace the size flags.
+        assert!(get_variants("--srcset-widths 320 --width 800").is_err());
+        assert!(get_variants("--srcset-densities 2 --emit html").is_err());
+    }
+
+    #[test]
+    fn check_plan_variants() {
+        let size_1080p = (1080, 1920);
+
+        // Widths are sorted and deduplicated.
+        assert_eq!(
+            Variants::Widths(vec![1280, 320, 640, 320]).plan(size_1080p, size_1080p),
+            vec![
+                (String::from("320w"), ResizeTarget::Dimensions((180, 320))),
+                (String::from("640w"), ResizeTarget::Dimensions((360, 640))),
+                (String::from("1280w"), ResizeTarget::Dimensions((720, 1280))),
+            ]
+        );
+
+        // Densities are relative to the `1x` dimensions.
+        assert_eq!(
+            Variants::Densities(vec![1, 2, 3]).plan(size_1080p, (270, 480)),
+            vec![
+                (String::from("1x"), ResizeTarget::Dimensions((270, 480))),
+                (String::from("2x"), ResizeTarget::Dimensions((540, 960))),
+                (String::from("3x"), ResizeTarget::Dimensions((810, 1440))),
+            ]
+        );
+    }
+
+    #[test]
+    fn check_targets_keep_dpi() {
+        // The density picked with the wizard reaches the saved file.
+        let size_spec = SizeSpec::Physical(None, Some(2), 300);
+        let resized_buffers = Targets::from_size_spec(&size_spec, (2, 4))
+            .resize(RgbaImage::new(4, 2), &ResizeOptions::default());
+        assert_eq!(resized_buffers.dpi, Some(300));
+
+        let resized_image =
+            ResizedImage::new(resized_buffers.image_buffer, 2, 4, None, Instant::now())
+                .with_dpi(resized_buffers.dpi);
+        let path = env::temp_dir().join(format!("picst-dpi-{}.png", process::id()));
+        save(
+            resized_image.get_buffer(),
+            &path,
+            resized_image.get_dpi().unwrap_or(DEFAULT_DPI),
+        )
+        .unwrap();
+        let bytes = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        // The `pHYs` chunk holds 11811 pixels per meter, i.e. 300 DPI.
+        let position = bytes
+            .windows(4)
+            .position(|window| window == b"pHYs")
+            .unwrap();
+        assert_eq!(&bytes[position + 4..position + 8], &11811u32.to_be_bytes());
+
+        // The other specifications leave the density to the flags.
+        let resized_buffers = Targets::from_size_spec(&SizeSpec::Pixels(None, Some(2)), (2, 4))
+            .resize(RgbaImage::new(4, 2), &ResizeOptions::default());
+        assert_eq!(resized_buffers.dpi, None);
+    }
+
+    #[test]
+    fn check_get_variant_path() {
+        assert_eq!(
+            get_variant_path(Path::new("/tmp/shot.png"), "640w"),
+            PathBuf::from("/tmp/shot-640w.png")
+        );
+        assert_eq!(
+            get_variant_path(Path::new("shot"), "2x"),
+            PathBuf::from("shot-2x.png")
+        );
+    }
+}
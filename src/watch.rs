@@ -0,0 +1,151 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use image::ImageFormat;
+
+/// Watcher of a directory, polled for the new image files.
+/// Note: the directory isn't walked recursively.
+#[derive(Debug)]
+pub(crate) struct DirWatcher {
+    directory: PathBuf,
+    /// New files along with their size at the previous poll, waiting for it to
+    /// settle.
+    pending: HashMap<PathBuf, u64>,
+    /// New files ready to be processed, in their order of appearance.
+    ready: VecDeque<PathBuf>,
+    /// Files already seen, ready or ignored.
+    seen: HashSet<PathBuf>,
+}
+
+impl DirWatcher {
+    /// Watches the directory, ignoring the files already there.
+    pub(crate) fn new(directory: &Path) -> Self {
+        let seen = list_images(directory)
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect();
+
+        Self {
+            directory: directory.to_path_buf(),
+            pending: HashMap::new(),
+            ready: VecDeque::new(),
+            seen,
+        }
+    }
+
+    /// Returns the watched directory.
+    pub(crate) fn get_directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// Ignores a file, e.g. a resized copy saved in the watched directory.
+    /// Note: the path might be spelled differently, e.g. relative, so only
+    /// the directories are compared.
+    pub(crate) fn ignore(&mut self, path: &Path) {
+        let Some(file_name) = path.file_name() else {
+            return;
+        };
+        let parent = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .unwrap_or_else(|| Path::new("."));
+
+        if fs::canonicalize(parent).ok() != fs::canonicalize(&self.directory).ok() {
+            return;
+        }
+
+        let path = self.directory.join(file_name);
+
+        self.pending.remove(&path);
+        self.seen.insert(path);
+    }
+
+    /// Puts back a file which couldn't be processed, so that it's the next
+    /// one.
+    pub(crate) fn retry(&mut self, path: PathBuf) {
+        self.ready.push_front(path);
+    }
+
+    /// Polls the directory and returns the next new file, if any.
+    /// The files being written, e.g. by a screenshot tool, are only returned
+    /// once their size is the same for two polls in a row.
+    pub(crate) fn next_file(&mut self) -> Option<PathBuf> {
+        let mut pending = HashMap::new();
+
+        for (path, size) in list_images(&self.directory) {
+            if self.seen.contains(&path) {
+                continue;
+            }
+
+            if size > 0 && self.pending.get(&path) == Some(&size) {
+                self.seen.insert(path.clone());
+                self.ready.push_back(path);
+            } else {
+                pending.insert(path, size);
+            }
+        }
+
+        self.pending = pending;
+
+        self.ready.pop_front()
+    }
+}
+
+/// Lists the image files of the directory along with their size, sorted by
+/// path.
+/// Note: a directory which can't be read, e.g. removed in the meantime, is
+/// considered empty.
+fn list_images(directory: &Path) -> Vec<(PathBuf, u64)> {
+    let Ok(entries) = fs::read_dir(directory) else {
+        return Vec::new();
+    };
+
+    let mut images = entries
+        .filter_map(Result::ok)
+        .filter(|entry| ImageFormat::from_path(entry.path()).is_ok())
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+
+            metadata.is_file().then(|| (entry.path(), metadata.len()))
+        })
+        .collect::<Vec<_>>();
+
+    images.sort();
+
+    images
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use super::DirWatcher;
+
+    #[test]
+    fn check_dir_watcher() {
+        let directory = env::temp_dir().join(format!("picst-watch-{}", process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(directory.join("old.png"), b"old").unwrap();
+
+        // The files already there are ignored.
+        let mut watcher = DirWatcher::new(&directory);
+        assert_eq!(watcher.next_file(), None);
+
+        // The new files are returned once their size settles.
+        fs::write(directory.join("new.png"), b"new").unwrap();
+        fs::write(directory.join("notes.txt"), b"notes").unwrap();
+        fs::write(directory.join("resized.png"), b"resized").unwrap();
+        watcher.ignore(&directory.join(".").join("resized.png"));
+        assert_eq!(watcher.next_file(), None);
+        assert_eq!(watcher.next_file(), Some(directory.join("new.png")));
+        assert_eq!(watcher.next_file(), None);
+
+        watcher.retry(directory.join("new.png"));
+        assert_eq!(watcher.next_file(), Some(directory.join("new.png")));
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
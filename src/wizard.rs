@@ -1,16 +1,33 @@
+use std::{env, iter::once};
+
 use anyhow::Result;
 use arboard::ImageData;
 use dialoguer::{console::Term, theme::ColorfulTheme, Input, Select};
+use image::{ImageBuffer, Rgba, RgbaImage};
+use indicatif::BinaryBytes;
 
+#[cfg(unix)]
+use crate::keys::while_prompting;
 use crate::{
+    adjustment::Adjustment,
     args::{Args, ArgsMetadata, ArgsResult},
     dimension::Dimension,
+    encoder::estimate_png_size,
+    hidpi::HiDpiScale,
+    physical::{get_dpi_from_index, get_dpi_items, PhysicalLength, PhysicalUnit},
+    planner::{plan, DimensionTuple, SizeSpec},
+    preview::{get_preview, Confirmation, Protocol},
+    skip::Skip,
     unit::Unit,
-    validation::{percent_validator, pixels_validator, ratio_validator},
+    validation::{
+        percent_validator, physical_length_validator, pixels_validator, ratio_validator,
+        size_validator,
+    },
 };
 
-/// Simple type alias.
-type DimensionTuple = (u32, u32);
+/// Environment variable holding the flags used instead of prompting the user,
+/// e.g. `--width-percent 50`.
+pub(crate) static DEFAULT_FLAGS_CONFIG: &str = "PICST_DEFAULT_FLAGS";
 
 /// Returns the dimension selected by the user.
 fn get_dimension_selector() -> Result<Dimension> {
@@ -23,11 +40,17 @@ fn get_dimension_selector() -> Result<Dimension> {
     index.try_into().map_err(anyhow::Error::msg)
 }
 
-/// Prompts the user for a dimension value.
-fn get_dimension_value_prompt(dimension: Dimension, is_pixel: bool) -> Result<u32> {
+/// Prompts the user for a dimension value, starting from the initial one if
+/// any.
+fn get_dimension_value_prompt(
+    dimension: Dimension,
+    is_pixel: bool,
+    initial: Option<u32>,
+) -> Result<u32> {
     // Use a prompt to get the desired value.
     let value: String = Input::with_theme(&ColorfulTheme::default())
         .with_prompt(dimension.get_name())
+        .with_initial_text(initial.map(|value| value.to_string()).unwrap_or_default())
         .validate_with({
             move |input: &String| -> Result<(), String> {
                 // Validate the input as pixels or percent.
@@ -44,11 +67,79 @@ fn get_dimension_value_prompt(dimension: Dimension, is_pixel: bool) -> Result<u3
     value.parse::<u32>().map_err(anyhow::Error::msg)
 }
 
-/// Prompts the user for a ratio.
-fn get_ratio_prompt() -> Result<f32> {
+/// Prompts the user for a dimension value as a physical length.
+fn get_physical_length_prompt(
+    dimension: Dimension,
+    physical_unit: PhysicalUnit,
+) -> Result<PhysicalLength> {
+    let suffix = physical_unit.get_suffix();
+
+    // Use a prompt to get the desired value.
+    let value: String = Input::with_theme(&ColorfulTheme::default())
+        .with_prompt(format!("{} ({suffix})", dimension.get_name()))
+        .validate_with({
+            move |input: &String| -> Result<(), String> {
+                // Validate the input as a length in the selected unit.
+                physical_length_validator(&format!("{input}{suffix}")).map(|_| ())
+            }
+        })
+        .interact_text()?;
+
+    // This can't fail since parsing has been safely checked above.
+    physical_length_validator(&format!("{value}{suffix}")).map_err(anyhow::Error::msg)
+}
+
+/// Returns the physical unit selected by the user.
+fn get_physical_unit_selector() -> Result<PhysicalUnit> {
+    // Use a select to get the physical unit.
+    let index = Select::with_theme(&ColorfulTheme::default())
+        .items(PhysicalUnit::get_items())
+        .default(0)
+        .interact_on(&Term::stderr())?;
+
+    index.try_into().map_err(anyhow::Error::msg)
+}
+
+/// Returns the dots per inch preset selected by the user.
+fn get_dpi_selector() -> Result<u16> {
+    // Use a select to get the density.
+    let index = Select::with_theme(&ColorfulTheme::default())
+        .items(get_dpi_items())
+        .default(0)
+        .interact_on(&Term::stderr())?;
+
+    get_dpi_from_index(index).map_err(anyhow::Error::msg)
+}
+
+/// Returns the physical unit and the dots per inch, the latter being only
+/// prompted if not passed via the flags.
+fn get_physical_settings(args: &Args) -> Result<(PhysicalUnit, u16)> {
+    let physical_unit = get_physical_unit_selector()?;
+    let dpi = match args.dpi {
+        Some(dpi) => dpi,
+        None => get_dpi_selector()?,
+    };
+
+    Ok((physical_unit, dpi))
+}
+
+/// Returns the high DPI scale selected by the user.
+fn get_hidpi_selector() -> Result<HiDpiScale> {
+    // Use a select to get the scale.
+    let index = Select::with_theme(&ColorfulTheme::default())
+        .items(HiDpiScale::get_items())
+        .default(0)
+        .interact_on(&Term::stderr())?;
+
+    index.try_into().map_err(anyhow::Error::msg)
+}
+
+/// Prompts the user for a ratio, starting from the initial one if any.
+fn get_ratio_prompt(initial: Option<f32>) -> Result<f32> {
     // Use a prompt to get the desired value.
     let value: String = Input::with_theme(&ColorfulTheme::default())
         .with_prompt("Ratio")
+        .with_initial_text(initial.map(|value| value.to_string()).unwrap_or_default())
         .validate_with({
             move |input: &String| -> Result<(), String> {
                 // Validate the input as ratio.
@@ -61,6 +152,35 @@ fn get_ratio_prompt() -> Result<f32> {
     value.parse::<f32>().map_err(anyhow::Error::msg)
 }
 
+/// Prompts the user for a size expression, starting from the initial one if
+/// any.
+/// Returns `None` if it's left empty, to go on with the guided prompts.
+fn get_size_expression_prompt(initial: Option<String>) -> Result<Option<SizeSpec>> {
+    // Use a prompt to get the desired expression.
+    let value: String = Input::with_theme(&ColorfulTheme::default())
+        .with_prompt("Size, e.g. 800, x300, 800x600!, 50% or 2/3 (empty to pick a unit)")
+        .with_initial_text(initial.unwrap_or_default())
+        .allow_empty(true)
+        .validate_with({
+            move |input: &String| -> Result<(), String> {
+                // Validate the input as a size expression, if any.
+                if input.trim().is_empty() {
+                    Ok(())
+                } else {
+                    size_validator(input).map(|_| ())
+                }
+            }
+        })
+        .interact_text()?;
+
+    if value.trim().is_empty() {
+        return Ok(None);
+    }
+
+    // This can't fail since parsing has been safely checked above.
+    size_validator(&value).map(Some).map_err(anyhow::Error::msg)
+}
+
 /// Returns the unit selected by the user.
 fn get_unit_selector() -> Result<Unit> {
     // Use a select to get the unit.
@@ -72,55 +192,117 @@ fn get_unit_selector() -> Result<Unit> {
     index.try_into().map_err(anyhow::Error::msg)
 }
 
-/// Takes an image and a ratio, returns the new dimensions as a tuple.
-fn apply_ratio_to_image(image: &ImageData, ratio: f32) -> DimensionTuple {
-    (
-        (image.height as f32 * ratio) as u32,
-        (image.width as f32 * ratio) as u32,
-    )
-}
+/// Creates the complete wizard used when no flags are passed.
+/// A size expression is prompted first, otherwise the unit is selected. In
+/// case of a ratio or a high DPI scale, simply prompt the user.
+/// Otherwise, prompt first for the height / width or both and then prompt for
+/// the necessary dimension(s).
+fn create_no_flags_wizard(args: &Args) -> Result<SizeSpec> {
+    if let Some(size_spec) = get_size_expression_prompt(None)? {
+        return Ok(size_spec);
+    }
 
-/// Conditionally applies a percent on the provided original size or directly
-/// returns the value.
-fn maybe_apply_percent(
-    original_size: usize,
-    target_size_pixels_or_percent: u32,
-    is_pixel: bool,
-) -> u32 {
-    if is_pixel {
-        target_size_pixels_or_percent
-    } else {
-        (original_size as u32 * target_size_pixels_or_percent) / 100
+    let unit = get_unit_selector()?;
+
+    if unit == Unit::Ratio {
+        return Ok(SizeSpec::Ratio(get_ratio_prompt(None)?));
     }
-}
 
-/// Takes the image dimensions, the current dimension and either apply the
-/// percentage or the inner ratio of the image.
-fn resize(
-    image_first_dimension: usize,
-    current_dimension: u32,
-    image_second_dimension: usize,
-    is_pixel: bool,
-) -> u32 {
-    // For percentage, we don't need to calculate any ratio since this is
-    // already a ratio on its own.
-    if !is_pixel {
-        return (image_second_dimension as u32 * current_dimension) / 100;
+    if unit == Unit::HiDpi {
+        return Ok(SizeSpec::HiDpi(get_hidpi_selector()?.get_factor()?));
     }
 
-    // We need to do some casting to keep the ratio correct.
-    let ratio: f32 = image_first_dimension as f32 / current_dimension as f32;
+    // Physical lengths are converted to pixels right away.
+    let dimensions_in_pixels = unit != Unit::Percentage;
+    let physical = if unit == Unit::Physical {
+        Some(get_physical_settings(args)?)
+    } else {
+        None
+    };
+    let get_value = |dimension: Dimension| -> Result<u32> {
+        match physical {
+            Some((physical_unit, dpi)) => {
+                Ok(get_physical_length_prompt(dimension, physical_unit)?.to_pixels(dpi))
+            }
+            None => get_dimension_value_prompt(dimension, dimensions_in_pixels, None),
+        }
+    };
+
+    // The missing dimension preserves the aspect ratio.
+    let (height, width) = match get_dimension_selector()? {
+        Dimension::Height => (Some(get_value(Dimension::Height)?), None),
+        Dimension::Width => (None, Some(get_value(Dimension::Width)?)),
+        Dimension::Both => (
+            Some(get_value(Dimension::Height)?),
+            Some(get_value(Dimension::Width)?),
+        ),
+    };
 
-    ((image_second_dimension as f32 / ratio).round()) as u32
+    // The density is kept for the saved files.
+    Ok(match physical {
+        Some((_, dpi)) => SizeSpec::Physical(height, width, dpi),
+        None => get_dimensions_spec(height, width, dimensions_in_pixels),
+    })
 }
 
-/// Creates a full wizard which returns a tuple of (height, width).
-/// It will prompt or not the user based on the parsed arguments.
-pub(crate) fn create_wizard(args: &Args, image: &ImageData) -> Result<DimensionTuple> {
-    match ArgsResult::get(args) {
-        // If dimensions are passed, we eventually need to prompt for the
-        // missing height or width.
-        // Note: we also need to take care of the aspect ratio.
+/// Prompts the user for the size specification whatever the flags, i.e. the
+/// settings wizard of the key controls or of the re-edited previews.
+pub(crate) fn prompt_settings(args: &Args) -> Result<SizeSpec> {
+    #[cfg(unix)]
+    return while_prompting(|| create_no_flags_wizard(args));
+
+    #[cfg(not(unix))]
+    create_no_flags_wizard(args)
+}
+
+/// Returns the confirmation selected by the user.
+fn get_confirmation_selector() -> Result<Confirmation> {
+    // Use a select to get the confirmation.
+    let index = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("Move it to the clipboard?")
+        .items(Confirmation::get_items())
+        .default(0)
+        .interact_on(&Term::stderr())?;
+
+    index.try_into().map_err(anyhow::Error::msg)
+}
+
+/// Previews the resized image in the terminal and asks the user for a
+/// confirmation.
+pub(crate) fn confirm_preview(
+    protocol: Protocol,
+    image_buffer: &RgbaImage,
+) -> Result<Confirmation> {
+    let prompt = || {
+        let term = Term::stderr();
+
+        term.write_str(&get_preview(protocol, image_buffer, term.size()))?;
+
+        get_confirmation_selector()
+    };
+
+    #[cfg(unix)]
+    return while_prompting(prompt);
+
+    #[cfg(not(unix))]
+    prompt()
+}
+
+/// Returns the size specification of the dimensions, either in pixels or in
+/// percent.
+fn get_dimensions_spec(height: Option<u32>, width: Option<u32>, is_pixel: bool) -> SizeSpec {
+    if is_pixel {
+        SizeSpec::Pixels(height, width)
+    } else {
+        SizeSpec::Percent(height, width)
+    }
+}
+
+/// Returns the size specification described by the arguments, or `None` if
+/// some values are missing and need to be prompted.
+fn get_flags_spec(args: &Args, image: &ImageData) -> Result<Option<SizeSpec>> {
+    let size_spec = match ArgsResult::get(args) {
+        // When ignoring the aspect ratio, both dimensions are needed.
         ArgsResult::Dimensions(
             height,
             width,
@@ -129,104 +311,277 @@ pub(crate) fn create_wizard(args: &Args, image: &ImageData) -> Result<DimensionT
                 ignore_aspect_ratio,
             },
         ) => {
-            let (height, width) = match (height, width, ignore_aspect_ratio) {
-                (Some(height), None, false) => (
-                    maybe_apply_percent(image.height, height, is_pixel),
-                    resize(image.height, height, image.width, is_pixel),
-                ),
-                (None, Some(width), false) => (
-                    resize(image.width, width, image.height, is_pixel),
-                    maybe_apply_percent(image.width, width, is_pixel),
-                ),
-                (Some(height), None, true) => (
-                    maybe_apply_percent(image.height, height, is_pixel),
-                    maybe_apply_percent(
-                        image.width,
-                        get_dimension_value_prompt(Dimension::Width, is_pixel)?,
-                        is_pixel,
-                    ),
-                ),
-                (None, Some(width), true) => (
-                    maybe_apply_percent(
-                        image.height,
-                        get_dimension_value_prompt(Dimension::Height, is_pixel)?,
-                        is_pixel,
-                    ),
-                    maybe_apply_percent(image.width, width, is_pixel),
-                ),
-                (Some(height), Some(width), false) => (
-                    maybe_apply_percent(image.height, height, is_pixel),
-                    maybe_apply_percent(image.width, width, is_pixel),
-                ),
-                // We can't have height, width and ignore aspect ratio.
-                _ => unreachable!(),
-            };
+            if ignore_aspect_ratio && (height.is_none() || width.is_none()) {
+                return Ok(None);
+            }
 
-            Ok((height, width))
+            get_dimensions_spec(height, width, is_pixel)
         }
-        ArgsResult::MaxByteSize(maximum_byte_size) => {
-            let current_byte_size = image.bytes.len();
-            let percentage = ((1.0 / (current_byte_size as f32 / maximum_byte_size as f32).sqrt())
-                * 100.0) as u32;
-
-            Ok((
-                resize(image.width, percentage, image.height, false),
-                resize(image.height, percentage, image.width, false),
+        ArgsResult::MaxByteSize(maximum_byte_size) => SizeSpec::MaxByteSize {
+            maximum: u64::from(maximum_byte_size),
+            current: image.bytes.len() as u64,
+        },
+        ArgsResult::PixelCount(budget) => SizeSpec::PixelCount(budget),
+        // The HiDPI scale is resolved to an integer factor.
+        ArgsResult::HiDpi(scale) => SizeSpec::HiDpi(scale.get_factor()?),
+        ArgsResult::NoFlags => return Ok(None),
+        // If a ratio is passed, just use it.
+        ArgsResult::Ratio(ratio) => SizeSpec::Ratio(ratio),
+        ArgsResult::Fit(height, width) => SizeSpec::Fit(height, width),
+        ArgsResult::Fill(height, width) => SizeSpec::Fill(height, width),
+    };
+
+    Ok(Some(size_spec))
+}
+
+/// Prompts the user for the values missing from the arguments.
+fn prompt_missing_values(args: &Args) -> Result<SizeSpec> {
+    match ArgsResult::get(args) {
+        // Only one dimension is passed while ignoring the aspect ratio, prompt
+        // for the other one.
+        ArgsResult::Dimensions(height, width, ArgsMetadata { is_pixel, .. }) => {
+            let get_value = |value: Option<u32>, dimension: Dimension| match value {
+                Some(value) => Ok(value),
+                None => get_dimension_value_prompt(dimension, is_pixel, None),
+            };
+
+            Ok(get_dimensions_spec(
+                Some(get_value(height, Dimension::Height)?),
+                Some(get_value(width, Dimension::Width)?),
+                is_pixel,
             ))
         }
-        // If no flags are passed, we first need to get the unit.
-        // In case of a ratio, simply prompt the user.
-        // Otherwise, prompt first for the height / width or both and then
-        // prompt for the necessary dimension(s).
-        // Note: we also need to take care of the aspect ratio.
-        ArgsResult::NoFlags => {
-            let unit = get_unit_selector()?;
-
-            if unit == Unit::Ratio {
-                let ratio = get_ratio_prompt()?;
-
-                // Return the new dimensions based on the ratio.
-                Ok(apply_ratio_to_image(image, ratio))
-            } else {
-                let dimensions_in_pixels = unit == Unit::Pixel;
-
-                let (height, width) = match get_dimension_selector()? {
-                    Dimension::Height => {
-                        let height =
-                            get_dimension_value_prompt(Dimension::Height, dimensions_in_pixels)?;
-
-                        (
-                            maybe_apply_percent(image.height, height, dimensions_in_pixels),
-                            resize(image.height, height, image.width, dimensions_in_pixels),
-                        )
-                    }
-                    Dimension::Width => {
-                        let width =
-                            get_dimension_value_prompt(Dimension::Width, dimensions_in_pixels)?;
-
-                        (
-                            resize(image.width, width, image.height, dimensions_in_pixels),
-                            maybe_apply_percent(image.width, width, dimensions_in_pixels),
-                        )
-                    }
-                    Dimension::Both => (
-                        maybe_apply_percent(
-                            image.height,
-                            get_dimension_value_prompt(Dimension::Height, dimensions_in_pixels)?,
-                            dimensions_in_pixels,
-                        ),
-                        maybe_apply_percent(
-                            image.width,
-                            get_dimension_value_prompt(Dimension::Width, dimensions_in_pixels)?,
-                            dimensions_in_pixels,
-                        ),
-                    ),
-                };
-
-                Ok((height, width))
-            }
+        // If no flags are passed, prompt for everything.
+        _ => create_no_flags_wizard(args),
+    }
+}
+
+/// Returns the adjustment selected by the user, the summary being the prompt.
+fn get_adjustment_selector(summary: &str) -> Result<Adjustment> {
+    // Use a select to get the adjustment.
+    let index = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt(summary)
+        .items(Adjustment::get_items())
+        .default(0)
+        .interact_on(&Term::stderr())?;
+
+    index.try_into().map_err(anyhow::Error::msg)
+}
+
+/// Prompts the user again for the values of the size specification, in the
+/// same unit.
+fn adjust_values(args: &Args, size_spec: SizeSpec) -> Result<SizeSpec> {
+    let get_value = |value: Option<u32>, dimension: Dimension, is_pixel: bool| {
+        value
+            .map(|value| get_dimension_value_prompt(dimension, is_pixel, Some(value)))
+            .transpose()
+    };
+
+    Ok(match size_spec {
+        SizeSpec::Pixels(height, width) => SizeSpec::Pixels(
+            get_value(height, Dimension::Height, true)?,
+            get_value(width, Dimension::Width, true)?,
+        ),
+        SizeSpec::Percent(height, width) => SizeSpec::Percent(
+            get_value(height, Dimension::Height, false)?,
+            get_value(width, Dimension::Width, false)?,
+        ),
+        // The converted lengths are adjusted in pixels, at the same density.
+        SizeSpec::Physical(height, width, dpi) => SizeSpec::Physical(
+            get_value(height, Dimension::Height, true)?,
+            get_value(width, Dimension::Width, true)?,
+            dpi,
+        ),
+        SizeSpec::Ratio(ratio) => SizeSpec::Ratio(get_ratio_prompt(Some(ratio))?),
+        SizeSpec::HiDpi(_) => SizeSpec::HiDpi(get_hidpi_selector()?.get_factor()?),
+        // The boxes only come from the size expressions, left as is if the
+        // expression is cleared.
+        SizeSpec::Fit(height, width) => {
+            get_size_expression_prompt(Some(format!("{width}x{height}")))?.unwrap_or(size_spec)
         }
-        // If a ratio is passed, just use it.
-        ArgsResult::Ratio(ratio) => Ok(apply_ratio_to_image(image, ratio)),
+        SizeSpec::Fill(height, width) => {
+            get_size_expression_prompt(Some(format!("{width}x{height}^")))?.unwrap_or(size_spec)
+        }
+        // The other specifications are never prompted.
+        SizeSpec::PixelCount(_) | SizeSpec::MaxByteSize { .. } => create_no_flags_wizard(args)?,
+    })
+}
+
+/// Returns the summary of the resize operation, from the dimensions as tuples
+/// of (height, width), e.g. `1920×1080 → 960×540 (50%), est. ~620.00 KiB PNG`.
+fn get_summary(
+    (height, width): DimensionTuple,
+    (new_height, new_width): DimensionTuple,
+    estimated_size: u64,
+) -> String {
+    let get_percent = |new_value: u32, value: u32| {
+        (f64::from(new_value) * 100.0 / f64::from(value.max(1))).round()
+    };
+    let (height_percent, width_percent) = (
+        get_percent(new_height, height),
+        get_percent(new_width, width),
+    );
+
+    // Both percentages are shown when the aspect ratio isn't preserved.
+    let percent = if height_percent == width_percent {
+        format!("{width_percent}%")
+    } else {
+        format!("{width_percent}% × {height_percent}%")
+    };
+
+    format!(
+        "{width}×{height} → {new_width}×{new_height} ({percent}), est. ~{} PNG",
+        BinaryBytes(estimated_size)
+    )
+}
+
+/// Shows the summary of the resize operation, letting the user adjust the
+/// values or pick another unit until it's accepted.
+fn confirm_size_spec(args: &Args, image: &ImageData, mut size_spec: SizeSpec) -> Result<SizeSpec> {
+    let dimensions = (image.height as u32, image.width as u32);
+    let image_buffer =
+        ImageBuffer::<Rgba<u8>, &[u8]>::from_raw(dimensions.1, dimensions.0, image.bytes.as_ref())
+            .ok_or_else(|| anyhow::Error::msg("Image data doesn't match its dimensions."))?;
+
+    loop {
+        let new_dimensions = plan(&size_spec, dimensions).get_dimensions(dimensions);
+        let estimated_size = estimate_png_size(&image_buffer, new_dimensions)?;
+
+        match get_adjustment_selector(&get_summary(dimensions, new_dimensions, estimated_size))? {
+            Adjustment::Accept => return Ok(size_spec),
+            Adjustment::Values => size_spec = adjust_values(args, size_spec)?,
+            Adjustment::Unit => size_spec = create_no_flags_wizard(args)?,
+        }
+    }
+}
+
+/// Returns the size specification of the default flags, used instead of
+/// prompting the user.
+/// The image is skipped if there are no default flags or if they are missing
+/// some values too.
+fn get_default_spec(default_flags: Option<&str>, image: &ImageData) -> Result<SizeSpec> {
+    let default_flags = default_flags.ok_or_else(|| {
+        Skip::new(format!(
+            "some values are missing and can't be prompted, pass them as flags or set `{DEFAULT_FLAGS_CONFIG}`."
+        ))
+    })?;
+    let default_args =
+        Args::try_custom_parse_from(once("picst").chain(default_flags.split_whitespace()))
+            .map_err(|error| {
+                // Only keep the first line, without the usage.
+                let error = error.to_string();
+                let message = error.lines().next().unwrap_or_default();
+
+                Skip::new(format!("`{DEFAULT_FLAGS_CONFIG}` is invalid, {message}"))
+            })?;
+
+    get_flags_spec(&default_args, image)?.ok_or_else(|| {
+        Skip::new(format!(
+            "the flags of `{DEFAULT_FLAGS_CONFIG}` are missing some values too."
+        ))
+        .into()
+    })
+}
+
+/// Creates a full wizard which returns the size specification of the resize
+/// operation.
+/// It will prompt or not the user based on the parsed arguments, the prompted
+/// values being confirmed along with the computed size.
+/// Without a terminal, or with `no_input`, the missing values are taken from
+/// the default flags instead.
+pub(crate) fn create_wizard(args: &Args, image: &ImageData) -> Result<SizeSpec> {
+    Ok(match get_flags_spec(args, image)? {
+        Some(size_spec) => size_spec,
+        #[cfg(unix)]
+        None if args.is_interactive() => {
+            while_prompting(|| confirm_size_spec(args, image, prompt_missing_values(args)?))?
+        }
+        #[cfg(not(unix))]
+        None if args.is_interactive() => {
+            confirm_size_spec(args, image, prompt_missing_values(args)?)?
+        }
+        None => get_default_spec(env::var(DEFAULT_FLAGS_CONFIG).ok().as_deref(), image)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use arboard::ImageData;
+    use clap::Parser;
+
+    use super::{get_default_spec, get_flags_spec, get_summary};
+    use crate::{args::Args, planner::SizeSpec, skip::Skip};
+
+    fn get_image() -> ImageData<'static> {
+        ImageData {
+            bytes: Cow::Owned(vec![0; 4 * 3 * 4]),
+            height: 3,
+            width: 4,
+        }
+    }
+
+    fn get_args_spec(flags: &str) -> Option<SizeSpec> {
+        let args = Args::parse_from(format!("picst {flags}").split_whitespace());
+
+        get_flags_spec(&args, &get_image()).unwrap()
+    }
+
+    #[test]
+    fn check_flags_spec() {
+        assert_eq!(
+            get_args_spec("--width 800"),
+            Some(SizeSpec::Pixels(None, Some(800)))
+        );
+        assert_eq!(
+            get_args_spec("--height-percent 50 --ignore-aspect-ratio --width-percent 20"),
+            Some(SizeSpec::Percent(Some(50), Some(20)))
+        );
+        assert_eq!(
+            get_args_spec("--maximum-byte-size 12"),
+            Some(SizeSpec::MaxByteSize {
+                maximum: 12,
+                current: 48
+            })
+        );
+
+        // Values to prompt.
+        assert_eq!(get_args_spec(""), None);
+        assert_eq!(get_args_spec("--width 800 --ignore-aspect-ratio"), None);
+    }
+
+    #[test]
+    fn check_default_spec() {
+        let image = get_image();
+
+        assert_eq!(
+            get_default_spec(Some("--width-percent 50"), &image).unwrap(),
+            SizeSpec::Percent(None, Some(50))
+        );
+
+        // Without usable default flags, the image is skipped.
+        for default_flags in [
+            None,
+            Some(""),
+            Some("--width zero"),
+            Some("--width 800 --ignore-aspect-ratio"),
+        ] {
+            let error = get_default_spec(default_flags, &image).unwrap_err();
+
+            assert!(error.is::<Skip>());
+        }
+    }
+
+    #[test]
+    fn check_summary() {
+        assert_eq!(
+            get_summary((1080, 1920), (540, 960), 634_880),
+            "1920×1080 → 960×540 (50%), est. ~620.00 KiB PNG"
+        );
+        assert_eq!(
+            get_summary((1080, 1920), (270, 960), 1024),
+            "1920×1080 → 960×270 (50% × 25%), est. ~1.00 KiB PNG"
+        );
     }
 }